@@ -0,0 +1,153 @@
+//! Constructors for pins that switch direction in place, so there are no
+//! conversion closures to spell out. Most HALs have such pins: flex or
+//! dynamic pins that change mode through `&mut self`, and open-drain outputs
+//! that can also be read, for which "input" just means releasing the line.
+//! Boards that wire the line to two pins, one driving it through a diode or
+//! resistor and one reading it, need no switching at all, see `DualPin`.
+use crate::units::Unit;
+use crate::HalfDuplexWire;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// A pin that changes direction without changing its type.
+pub trait PinMode: InputPin + OutputPin {
+    /// Stops driving the line.
+    fn set_input_mode(&mut self);
+    fn set_output_mode(&mut self);
+}
+
+/// An open-drain output that can read back the line. It is never switched
+/// to input: driving it high releases the line to the pull-up.
+pub struct OpenDrain<P>(pub P);
+
+impl<P: InputPin> InputPin for OpenDrain<P> {
+    type Error = P::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        return self.0.is_high();
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        return self.0.is_low();
+    }
+}
+
+impl<P: OutputPin> OutputPin for OpenDrain<P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        return self.0.set_low();
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        return self.0.set_high();
+    }
+}
+
+impl<P: InputPin + OutputPin> PinMode for OpenDrain<P> {
+    fn set_input_mode(&mut self) {
+        self.0.set_high().ok();
+    }
+
+    fn set_output_mode(&mut self) {}
+}
+
+/// A permanent input and a permanent output on the same line. Switching
+/// direction only releases or keeps driving the output, so there is no mode
+/// switch dead time to budget for.
+pub struct DualPin<I, O> {
+    input: I,
+    output: O,
+}
+
+impl<I, O> DualPin<I, O> {
+    pub fn new(input: I, output: O) -> Self {
+        DualPin {
+            input: input,
+            output: output,
+        }
+    }
+
+    pub fn into_parts(self) -> (I, O) {
+        return (self.input, self.output);
+    }
+}
+
+impl<I: InputPin, O> InputPin for DualPin<I, O> {
+    type Error = I::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        return self.input.is_high();
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        return self.input.is_low();
+    }
+}
+
+impl<I, O: OutputPin> OutputPin for DualPin<I, O> {
+    type Error = O::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        return self.output.set_low();
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        return self.output.set_high();
+    }
+}
+
+impl<I: InputPin, O: OutputPin> PinMode for DualPin<I, O> {
+    fn set_input_mode(&mut self) {
+        self.output.set_high().ok();
+    }
+
+    fn set_output_mode(&mut self) {}
+}
+
+fn to_input<P: PinMode>(mut pin: P) -> P {
+    pin.set_input_mode();
+    return pin;
+}
+
+fn to_output<P: PinMode>(mut pin: P) -> P {
+    pin.set_output_mode();
+    return pin;
+}
+
+pub type DynamicWire<P, T> = HalfDuplexWire<fn(P) -> P, fn(P) -> P, P, P, T>;
+
+impl<P, T> HalfDuplexWire<fn(P) -> P, fn(P) -> P, P, P, T>
+where
+    P: PinMode,
+    T: Unit,
+{
+    /// Like `new`, for a pin that switches direction itself. The pin is put
+    /// into input mode first.
+    pub fn from_dynamic_pin(mut pin: P, delay: T) -> Self {
+        pin.set_input_mode();
+        return HalfDuplexWire::new(pin, to_output::<P>, to_input::<P>, delay);
+    }
+}
+
+impl<P, T> DynamicWire<OpenDrain<P>, T>
+where
+    P: InputPin + OutputPin,
+    T: Unit,
+{
+    pub fn from_open_drain_pin(pin: P, delay: T) -> Self {
+        return HalfDuplexWire::from_dynamic_pin(OpenDrain(pin), delay);
+    }
+}
+
+impl<IP, OP, T> DynamicWire<DualPin<IP, OP>, T>
+where
+    IP: InputPin,
+    OP: OutputPin,
+    T: Unit,
+{
+    /// Like `new`, for a line read through `input` and driven through
+    /// `output`. `release` hands both back as a `DualPin`.
+    pub fn from_dual_pins(input: IP, output: OP, delay: T) -> Self {
+        return HalfDuplexWire::from_dynamic_pin(DualPin::new(input, output), delay);
+    }
+}
@@ -0,0 +1,82 @@
+//! Addressing for multi-drop wires. Every addressed transfer starts with the
+//! destination byte:
+//!
+//! - `BROADCAST` is accepted by every device,
+//! - `GROUPS` are accepted by the members set in `Config::groups`,
+//! - everything else is a single device address.
+//!
+//! Broadcast and group traffic is never acknowledged, as several answers
+//! would collide.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use core::ops::RangeInclusive;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub type Addr = u8;
+
+pub const BROADCAST: Addr = 0xff;
+pub const GROUPS: RangeInclusive<Addr> = 0xf0..=0xfe;
+
+/// Whether `addr` reaches more than one device.
+pub fn is_multicast(addr: Addr) -> bool {
+    return addr == BROADCAST || GROUPS.contains(&addr);
+}
+
+/// Bit in `Config::groups` for a group address, `None` for other addresses.
+pub fn group_bit(addr: Addr) -> Option<u16> {
+    if GROUPS.contains(&addr) {
+        return Some(1 << (addr - GROUPS.start()));
+    }
+    return None;
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Whether traffic for `addr` is meant for us. A wire without an own
+    /// address accepts everything.
+    pub fn accepts(&self, addr: Addr) -> bool {
+        let own = match self.config.address {
+            Some(s) => s,
+            None => return true,
+        };
+
+        if addr == own || addr == BROADCAST {
+            return true;
+        }
+        return match group_bit(addr) {
+            Some(bit) => self.config.groups & bit != 0,
+            None => false,
+        };
+    }
+
+    pub fn write_to(
+        &mut self,
+        addr: Addr,
+        data: u8,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        self.write(addr, delay)?;
+        return self.write(data, delay);
+    }
+
+    /// Returns the next byte sent to an address we accept, together with that
+    /// address, and skips traffic for other devices.
+    pub fn read_addressed(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<(Addr, u8), Error> {
+        loop {
+            let addr = self.read(delay)?;
+            let data = self.read_timeout(self.config.response_timeout, delay)?;
+
+            if self.accepts(addr) {
+                return Ok((addr, data));
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Console-safe transport: every byte travels as two upper-case hex digits
+//! and frames are terminated by a newline, so traffic can be read or typed
+//! by hand on a shared UART console.
+//!
+//! As a `ReadWrite` and `PacketTransport` it carries frames, checked
+//! transfers, messages and packets like the wire does. Blanks and line
+//! breaks between bytes are skipped, so a packet may be typed on one line.
+use crate::config::Config;
+use crate::packet::{Link, PacketTransport};
+use crate::units::PhaseDelay;
+use crate::{Error, ReadWrite};
+use embedded_hal::serial;
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+pub struct AsciiWire<S> {
+    serial: S,
+    config: Config,
+    link: Link,
+}
+
+impl<S> AsciiWire<S>
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    pub fn new(serial: S) -> Self {
+        AsciiWire {
+            serial: serial,
+            config: Config::default(),
+            link: Link::default(),
+        }
+    }
+
+    /// Picks checksums and packet limits for traffic typed on the console,
+    /// e.g. `crc: false` so packets can be entered without computing one.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        return self;
+    }
+
+    pub fn release(self) -> S {
+        return self.serial;
+    }
+
+    fn put(&mut self, c: u8) -> Result<(), Error> {
+        return io_err!(nb::block!(self.serial.write(c)));
+    }
+
+    fn take(&mut self) -> Result<u8, Error> {
+        return io_err!(nb::block!(self.serial.read()));
+    }
+
+    pub fn write(&mut self, data: u8) -> Result<(), Error> {
+        self.put(HEX[(data >> 4) as usize])?;
+        self.put(HEX[(data & 0x0f) as usize])?;
+        return Ok(());
+    }
+
+    pub fn read(&mut self) -> Result<u8, Error> {
+        let hi = loop {
+            match self.take()? {
+                b' ' | b'\t' | b'\r' | b'\n' => continue,
+                c => break nibble(c)?,
+            }
+        };
+        let lo = nibble(self.take()?)?;
+
+        return Ok(hi << 4 | lo);
+    }
+
+    /// Writes `data` as one hex line terminated by `\r\n`.
+    pub fn write_frame(&mut self, data: &[u8]) -> Result<(), Error> {
+        for &byte in data {
+            self.write(byte)?;
+        }
+        self.put(b'\r')?;
+        self.put(b'\n')?;
+
+        return io_err!(nb::block!(self.serial.flush()));
+    }
+
+    /// Reads one hex line into `buf` and returns the number of bytes decoded.
+    /// Blanks between digits and empty lines are ignored.
+    pub fn read_frame(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut len = 0;
+        let mut high: Option<u8> = None;
+
+        loop {
+            match self.take()? {
+                b' ' | b'\t' | b'\r' => continue,
+                b'\n' => {
+                    if high.is_some() {
+                        return Err(Error::Framing);
+                    }
+                    if len > 0 {
+                        return Ok(len);
+                    }
+                }
+                c => {
+                    let n = nibble(c)?;
+                    match high.take() {
+                        None => high = Some(n),
+                        Some(h) => {
+                            if len == buf.len() {
+                                return Err(Error::Framing);
+                            }
+                            buf[len] = h << 4 | n;
+                            len += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S, T> ReadWrite<T> for AsciiWire<S>
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    fn write(&mut self, data: u8, _delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return AsciiWire::write(self, data);
+    }
+
+    fn read(&mut self, _delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return AsciiWire::read(self);
+    }
+
+    fn config(&self) -> Config {
+        return self.config;
+    }
+}
+
+impl<S, T> PacketTransport<T> for AsciiWire<S>
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    fn link(&mut self) -> &mut Link {
+        return &mut self.link;
+    }
+}
+
+fn nibble(c: u8) -> Result<u8, Error> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::Framing),
+    }
+}
@@ -0,0 +1,74 @@
+//! Listen-before-talk. With `Config::csma` set, a write that finds the bus
+//! busy waits for the line to go idle, then for a random number of frame
+//! slots, and tries again. The window doubles with every attempt, so
+//! contending senders spread out instead of colliding in lockstep.
+//!
+//! The random numbers come from a 16-bit xorshift generator. Devices that
+//! start from the same seed back off in lockstep, so seed it per device with
+//! `seed_backoff`, e.g. from a hardware RNG, a serial number or a free-running
+//! counter.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::HalfDuplexWire;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// The window stops doubling at this many slots.
+pub const MAX_BACKOFF_SLOTS: u16 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Backoff {
+    state: u16,
+}
+
+impl Backoff {
+    pub(crate) const fn new() -> Self {
+        Backoff { state: 0xace1 }
+    }
+
+    fn next(&mut self) -> u16 {
+        let mut x = self.state;
+        x ^= x << 7;
+        x ^= x >> 9;
+        x ^= x << 8;
+        self.state = x;
+        return x;
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Seeds the backoff generator. Zero, which would never change, is
+    /// replaced by the default seed.
+    pub fn seed_backoff(&mut self, seed: u16) {
+        self.backoff = if seed == 0 {
+            Backoff::new()
+        } else {
+            Backoff { state: seed }
+        };
+    }
+
+    /// Phases of one frame, the backoff slot.
+    fn slot_phases(&self) -> u16 {
+        let bits = 8 + self.config.parity.bits() as u16;
+        return self.config.start_phases as u16 + 8 * bits + self.config.frame_gap as u16;
+    }
+
+    /// Waits for the bus to go idle, then for a random number of slots out of
+    /// a window of `2^(attempt + 1)`.
+    pub(crate) fn back_off(&mut self, attempt: u8, delay: &mut impl PhaseDelay<T>) {
+        self.wait_retry_gap(delay);
+
+        let window = (2u16 << attempt.min(5)).min(MAX_BACKOFF_SLOTS);
+        let slots = self.backoff.next() % window;
+        for _ in 0..slots as u32 * self.slot_phases() as u32 {
+            self.phase(delay);
+        }
+    }
+}
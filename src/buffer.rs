@@ -0,0 +1,89 @@
+//! Received bytes for the application to consume at its own pace. As the
+//! observer of a wire, an `RxBuffer` collects every byte any receive path
+//! completes, blocking, polled or decoded from edges an interrupt handler
+//! captured; the application drains it later through `observer_mut`.
+//!
+//! ```
+//! # use half_duplex_wire::buffer::RxBuffer;
+//! # use half_duplex_wire::testing::{mock_wire, Line};
+//! let line = Line::new(10);
+//! let mut wire = mock_wire(&line).with_observer(RxBuffer::<16>::new());
+//!
+//! line.script_byte(0x42);
+//! wire.read(&mut line.delay()).unwrap();
+//! assert_eq!(wire.observer_mut().pop(), Some(0x42));
+//! ```
+use crate::observer::{Direction, WireObserver};
+use crate::ring::Ring;
+
+/// Holds up to `N` bytes; while it is full, new ones are dropped and counted.
+pub struct RxBuffer<const N: usize> {
+    bytes: Ring<u8, N>,
+    overflows: u32,
+}
+
+impl<const N: usize> RxBuffer<N> {
+    pub const fn new() -> Self {
+        RxBuffer {
+            bytes: Ring::new(),
+            overflows: 0,
+        }
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        if self.bytes.push(byte).is_err() {
+            self.overflows = self.overflows.wrapping_add(1);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<u8> {
+        return self.bytes.pop();
+    }
+
+    /// Moves as many bytes as fit into `buf` and returns their count.
+    pub fn pop_into(&mut self, buf: &mut [u8]) -> usize {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.bytes.pop() {
+                Some(byte) => buf[n] = byte,
+                None => break,
+            }
+            n += 1;
+        }
+        return n;
+    }
+
+    /// Bytes waiting to be popped.
+    pub fn available(&self) -> usize {
+        return self.bytes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.bytes.len() == 0;
+    }
+
+    /// Bytes lost to a full buffer since it was created.
+    pub fn overflows(&self) -> u32 {
+        return self.overflows;
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+}
+
+impl<const N: usize> Default for RxBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> WireObserver for RxBuffer<N> {
+    fn on_frame(&mut self, dir: Direction, bytes: &[u8]) {
+        if dir == Direction::Rx {
+            for &byte in bytes {
+                self.push(byte);
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Checked construction. `HalfDuplexWire::new` takes everything as given;
+//! the builder collects the options and validates them together.
+use crate::config::{BitOrder, Config, ConfigError, Parity, Timing};
+use crate::units::Unit;
+use crate::HalfDuplexWire;
+use core::marker::PhantomData;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub struct WireBuilder<F2, F1, I, O, T> {
+    pin: I,
+    into_output: F2,
+    into_input: F1,
+    delay: T,
+    config: Config,
+    output: PhantomData<O>,
+}
+
+/// The wire, or what was wrong together with the builder to correct it.
+pub type BuildResult<F2, F1, I, O, T> =
+    Result<HalfDuplexWire<F2, F1, I, O, T>, (ConfigError, WireBuilder<F2, F1, I, O, T>)>;
+
+impl<F2, F1, I, O, T> HalfDuplexWire<F2, F1, I, O, T>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Unit,
+{
+    /// Starts from `Config::default()`; the arguments are those of `new`.
+    pub fn builder(
+        pin: I,
+        into_output: F2,
+        into_input: F1,
+        delay: T,
+    ) -> WireBuilder<F2, F1, I, O, T> {
+        WireBuilder {
+            pin: pin,
+            into_output: into_output,
+            into_input: into_input,
+            delay: delay,
+            config: Config::default(),
+            output: PhantomData,
+        }
+    }
+}
+
+impl<F2, F1, I, O, T> WireBuilder<F2, F1, I, O, T>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Unit,
+{
+    /// Replaces all options at once; later calls adjust single fields.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        return self;
+    }
+
+    pub fn timing(mut self, timing: Timing) -> Self {
+        self.config.timing = timing;
+        return self;
+    }
+
+    pub fn crc(mut self, crc: bool) -> Self {
+        self.config.crc = crc;
+        return self;
+    }
+
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.config.parity = parity;
+        return self;
+    }
+
+    pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.config.bit_order = bit_order;
+        return self;
+    }
+
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.config.retries = retries;
+        return self;
+    }
+
+    /// Retry a busy bus up to `attempts` times with random backoff.
+    pub fn csma(mut self, attempts: u8) -> Self {
+        self.config.csma = Some(attempts);
+        return self;
+    }
+
+    pub fn address(mut self, address: u8) -> Self {
+        self.config.address = Some(address);
+        return self;
+    }
+
+    /// Largest packet payload, usually the size of the receive buffer.
+    pub fn max_payload(mut self, max_payload: u8) -> Self {
+        self.config.max_payload = max_payload;
+        return self;
+    }
+
+    /// Validates the options and builds the wire. On failure the builder
+    /// comes back with the error, to be corrected or taken apart with
+    /// `into_parts`.
+    pub fn build(self) -> BuildResult<F2, F1, I, O, T> {
+        if self.delay.as_micros() == 0 {
+            return Err((ConfigError::ZeroPhase, self));
+        }
+        if let Err(e) = self.config.validate() {
+            return Err((e, self));
+        }
+
+        let wire = HalfDuplexWire::new(self.pin, self.into_output, self.into_input, self.delay);
+        return Ok(wire.with_config(self.config));
+    }
+
+    /// Hands back what `builder` was given.
+    pub fn into_parts(self) -> (I, F2, F1, T) {
+        return (self.pin, self.into_output, self.into_input, self.delay);
+    }
+}
@@ -0,0 +1,94 @@
+//! Timing calibration against a peer with a different clock. The master
+//! sends the `TRAINING` byte 0x55; the slave times it with its own delay and
+//! derives how many of its ticks make up one of the master's phases.
+//!
+//! Every bit starts with a rising edge and lasts 8 phases, and the line is
+//! released one bit period after the last rising edge, so the first and the
+//! ninth rising edge are exactly 64 phases apart.
+use crate::config::Timing;
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{EdgeDetector, Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const TRAINING: u8 = 0x55;
+const TRAINING_PHASES: u32 = 64;
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Master side: sends the training pattern, never scrambled.
+    pub fn send_training(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        let result = self.write_byte(TRAINING, delay);
+        return self.settle(result);
+    }
+
+    /// Slave side: measures the training pattern and adopts the result as
+    /// `Config::timing`. The configured delay should be several times
+    /// shorter than the master's phase, it is the resolution of the result.
+    /// A line without the pattern fails with `Error::Calibration` once the
+    /// longest `Timing` it could measure has passed.
+    pub fn calibrate(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<Timing, Error> {
+        let pin = match self.pin.take() {
+            Some(s) => s,
+            None => return Err(Error::Unavailable),
+        };
+
+        let mut ed = EdgeDetector::with_filter(pin, self.config.filter_depth);
+        let result = self.measure_training(&mut ed, delay);
+        self.bring_back_pin(ed.release());
+
+        let ticks = self.settle(result)?;
+        let timing = Timing {
+            ticks_per_phase: ((ticks + TRAINING_PHASES / 2) / TRAINING_PHASES) as u16,
+        };
+        if timing.ticks_per_phase == 0 {
+            return self.settle(Err(Error::Calibration));
+        }
+
+        self.config.timing = timing;
+        if !self.low_power {
+            self.timing = timing;
+        }
+        return Ok(timing);
+    }
+
+    fn measure_training(
+        &mut self,
+        ed: &mut EdgeDetector<I>,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<u32, Error> {
+        let limit = TRAINING_PHASES * u16::MAX as u32;
+
+        let mut waited = 0u32;
+        while !ed.risig_edge() {
+            delay.delay_phase(self.delay);
+            waited += 1;
+            if waited > limit {
+                return Err(Error::Calibration);
+            }
+        }
+
+        let mut ticks = 0u32;
+        let mut edges = 0;
+        while edges < 8 {
+            delay.delay_phase(self.delay);
+            ticks += 1;
+
+            if ed.risig_edge() {
+                edges += 1;
+            }
+            if ticks > limit {
+                return Err(Error::Calibration);
+            }
+        }
+
+        return Ok(ticks);
+    }
+}
@@ -0,0 +1,150 @@
+//! Receive path driven by captured edge timestamps instead of delays. An
+//! input-capture timer, or a GPIO interrupt reading a monotonic clock,
+//! records when the line changed; pulse widths are then measured exactly
+//! and decoded by `coding::Decoder`.
+use crate::coding::{self, Decoder, Pulse};
+use crate::config::Encoding;
+use crate::observer::{Direction, WireObserver};
+use crate::ring::Ring;
+use crate::snapshot::Activity;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub rising: bool,
+    /// Timer count at the edge. Counts may wrap around.
+    pub at: u32,
+}
+
+pub trait PulseTimer {
+    /// Returns the oldest edge not handed out yet.
+    fn capture(&mut self) -> nb::Result<Edge, Error>;
+}
+
+/// Edge log for interrupt-driven capture: the ISR pushes, the decoder pops.
+pub struct EdgeBuffer<const N: usize> {
+    edges: Ring<Edge, N>,
+    overflow: bool,
+}
+
+impl<const N: usize> EdgeBuffer<N> {
+    pub const fn new() -> Self {
+        EdgeBuffer {
+            edges: Ring::new(),
+            overflow: false,
+        }
+    }
+
+    /// Records an edge, dropping it if the buffer is full.
+    pub fn push(&mut self, edge: Edge) {
+        if self.edges.push(edge).is_err() {
+            self.overflow = true;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.edges.clear();
+        self.overflow = false;
+    }
+}
+
+impl<const N: usize> Default for EdgeBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> PulseTimer for EdgeBuffer<N> {
+    /// Fails with `Error::Framing` once edges were lost.
+    fn capture(&mut self) -> nb::Result<Edge, Error> {
+        if self.overflow {
+            return Err(nb::Error::Other(Error::Framing));
+        }
+        return self.edges.pop().ok_or(nb::Error::WouldBlock);
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Decodes one byte from captured edges; `ticks_per_phase` relates timer
+    /// counts to the phase duration. Pulses are classified by
+    /// `Config::pulse_bands`. Only pulse-width coding is supported: the
+    /// frame is over at the edge that releases the line after its last bit,
+    /// while a Manchester frame ending in a one has no edge after it. A
+    /// `ticks_per_phase` of zero is `Error::Config`.
+    pub fn read_captured(
+        &mut self,
+        timer: &mut impl PulseTimer,
+        ticks_per_phase: u32,
+    ) -> Result<u8, Error> {
+        if self.config.encoding != Encoding::PulseWidth {
+            return Err(Error::Unavailable);
+        }
+        if ticks_per_phase == 0 {
+            return Err(Error::Config);
+        }
+
+        let pin = match self.pin.take() {
+            Some(s) => s,
+            None => return Err(Error::Unavailable),
+        };
+
+        self.activity = Activity::Receiving;
+        self.inflight = 0;
+        self.bits = 0;
+        let mut decoder = Decoder::new(&self.config);
+        let result = self.decode_captured(timer, ticks_per_phase, &mut decoder);
+        self.bring_back_pin(pin);
+
+        let mut buf = [0u8; 1];
+        let result = result.map(|word| self.deliver_rx(word, &mut buf));
+        self.settle(result)?;
+        return Ok(buf[0]);
+    }
+
+    /// Feeds the time between edges to `decoder`. Levels before the first
+    /// edge are unknown, so a rising edge left over from the previous frame
+    /// only marks where the next level starts.
+    fn decode_captured(
+        &mut self,
+        timer: &mut impl PulseTimer,
+        ticks_per_phase: u32,
+        decoder: &mut Decoder,
+    ) -> Result<u32, Error> {
+        let mut last: Option<Edge> = None;
+
+        loop {
+            let edge = nb::block!(timer.capture())?;
+
+            if let Some(prev) = last {
+                let ticks = edge.at.wrapping_sub(prev.at) as u64;
+                let eighths = (ticks * 8 / ticks_per_phase as u64).min(u32::MAX as u64) as u32;
+                if prev.rising && decoder.in_frame() {
+                    if let Ok(Pulse::Bit(bit)) = coding::classify(&self.config.pulse_bands, eighths)
+                    {
+                        self.observer.on_bit(Direction::Rx, bit);
+                    }
+                }
+                let word = decoder.feed(prev.rising, eighths)?;
+                self.inflight = decoder.word() as u8;
+                self.bits = decoder.bits();
+                if let Some(word) = word {
+                    return Ok(word);
+                }
+                // The line released after the last bit.
+                if edge.rising && decoder.is_complete() {
+                    return decoder.finish();
+                }
+            }
+            last = Some(edge);
+        }
+    }
+}
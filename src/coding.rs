@@ -0,0 +1,336 @@
+//! Pin-free line coding. `Encoder` turns a frame into the line levels the
+//! wire drives, `Decoder` turns what a receiver saw back into the frame;
+//! neither needs a pin or a delay, so both run on the host under tests and
+//! fuzzers. The blocking, polled and captured transfers only move the pin
+//! and keep time around them: what the levels mean is decided here.
+//!
+//! Durations are counted in phases on the way out and in eighths of a phase
+//! on the way in, the unit of `PulseBands`. Frames carry raw symbols, most
+//! significant bit first: bit order and scrambling stay outside, applied
+//! before encoding and undone after decoding.
+use crate::config::{Config, Encoding, Parity, PulseBands};
+use crate::manchester::MANCHESTER_HALF;
+use crate::Error;
+
+/// The line held at one level for a while.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub high: bool,
+    pub phases: u8,
+    /// The bit whose first half this is. `None` for the start condition,
+    /// the Manchester sync bit and second halves.
+    pub bit: Option<bool>,
+}
+
+const fn seg(high: bool, phases: u8) -> Segment {
+    return Segment {
+        high: high,
+        phases: phases,
+        bit: None,
+    };
+}
+
+/// The two halves of a bit.
+pub fn bit_segments(encoding: Encoding, bit: bool) -> [Segment; 2] {
+    return match (encoding, bit) {
+        (Encoding::PulseWidth, true) => [seg(true, 4), seg(false, 4)],
+        (Encoding::PulseWidth, false) => [seg(true, 2), seg(false, 6)],
+        (Encoding::Manchester, true) => [seg(false, MANCHESTER_HALF), seg(true, MANCHESTER_HALF)],
+        (Encoding::Manchester, false) => [seg(true, MANCHESTER_HALF), seg(false, MANCHESTER_HALF)],
+    };
+}
+
+/// What a high pulse of pulse-width coding stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pulse {
+    Bit(bool),
+    /// The idle line after the last bit.
+    Stop,
+}
+
+/// Classifies a high pulse of `eighths` eighths of a phase.
+pub fn classify(bands: &PulseBands, eighths: u32) -> Result<Pulse, Error> {
+    let within = |(min, max): (u8, u8)| (min as u32..=max as u32).contains(&eighths);
+    if eighths > bands.stop as u32 {
+        return Ok(Pulse::Stop);
+    }
+    if within(bands.zero) {
+        return Ok(Pulse::Bit(false));
+    }
+    if within(bands.one) {
+        return Ok(Pulse::Bit(true));
+    }
+    return Err(Error::InvalidPulse);
+}
+
+/// Line levels of one frame, see `encode_word`. Neighbouring segments may
+/// have the same level, as Manchester halves do; on the line they are one.
+pub struct Encoder {
+    encoding: Encoding,
+    start: u8,
+    word: u32,
+    bits: u8,
+    parity: Option<bool>,
+    sync: bool,
+    pos: u8,
+}
+
+impl Encoder {
+    /// Bit `n` of the frame, counting the sync bit.
+    fn symbol(&self, n: u8) -> Option<bool> {
+        let n = n - self.sync as u8;
+        if n < self.bits {
+            return Some(self.word >> (self.bits - 1 - n) & 1 != 0);
+        }
+        if n == self.bits {
+            return self.parity;
+        }
+        return None;
+    }
+}
+
+impl Iterator for Encoder {
+    type Item = Segment;
+
+    fn next(&mut self) -> Option<Segment> {
+        if self.pos == 0 {
+            self.pos = 1;
+            return Some(seg(false, self.start));
+        }
+
+        let n = (self.pos - 1) / 2;
+        let second = (self.pos - 1) % 2 == 1;
+        let sync = self.sync && n == 0;
+        let bit = if sync { false } else { self.symbol(n)? };
+        self.pos += 1;
+
+        let mut segment = bit_segments(self.encoding, bit)[second as usize];
+        if !second && !sync {
+            segment.bit = Some(bit);
+        }
+        return Some(segment);
+    }
+}
+
+/// The frame for the low `bits` bits of `word`, at most 32, from the start
+/// condition to the last segment; the line is idle, high, after it.
+pub fn encode_word(config: &Config, word: u32, bits: u8) -> Encoder {
+    return Encoder {
+        encoding: config.encoding,
+        start: config.start_phases,
+        word: word,
+        bits: bits,
+        parity: config.parity.bit(word),
+        sync: config.encoding == Encoding::Manchester,
+        pos: 0,
+    };
+}
+
+/// The frame `write` sends for `byte` under `config`, unscrambled.
+pub fn encode_byte(config: &Config, byte: u8) -> Encoder {
+    return encode_word(config, config.bit_order.apply(byte) as u32, 8);
+}
+
+/// Assembles frames from what a receiver saw. The blocking transfers push
+/// the bits they sampled, `feed` takes line levels and their durations,
+/// e.g. from a capture timer or a logic analyzer dump. Either way a frame
+/// ends with `Pulse::Stop` and comes out as the raw word `encode_word` took.
+pub struct Decoder {
+    encoding: Encoding,
+    parity: Parity,
+    bands: PulseBands,
+    start: u32,
+    expected: u8,
+    in_frame: bool,
+    synced: bool,
+    data: u32,
+    bits: u8,
+    parity_bit: Option<bool>,
+    overlong: bool,
+    half: Option<bool>,
+}
+
+impl Decoder {
+    /// A decoder for frames of one byte.
+    pub fn new(config: &Config) -> Self {
+        return Decoder::with_bits(config, 8);
+    }
+
+    /// A decoder for frames of `bits` data bits, at most 32.
+    pub fn with_bits(config: &Config, bits: u8) -> Self {
+        return Decoder {
+            encoding: config.encoding,
+            parity: config.parity,
+            bands: config.pulse_bands,
+            start: config.start_phases as u32 * 8,
+            expected: bits,
+            in_frame: false,
+            synced: false,
+            data: 0,
+            bits: 0,
+            parity_bit: None,
+            overlong: false,
+            half: None,
+        };
+    }
+
+    /// Starts a frame, for receivers that saw the start condition
+    /// themselves.
+    pub fn begin(&mut self) {
+        self.in_frame = true;
+        self.synced = self.encoding != Encoding::Manchester;
+        self.data = 0;
+        self.bits = 0;
+        self.parity_bit = None;
+        self.overlong = false;
+        self.half = None;
+    }
+
+    pub fn in_frame(&self) -> bool {
+        return self.in_frame;
+    }
+
+    /// Data bits of the current frame so far.
+    pub fn bits(&self) -> u8 {
+        return self.bits;
+    }
+
+    /// The data bits of the current frame so far, the last one lowest.
+    pub fn word(&self) -> u32 {
+        return self.data;
+    }
+
+    /// Whether every bit of the frame is in, so only the stop is missing.
+    pub fn is_complete(&self) -> bool {
+        let parity_done = self.parity == Parity::None || self.parity_bit.is_some();
+        return self.in_frame && self.bits == self.expected && parity_done;
+    }
+
+    /// Adds a bit to the frame, the sync bit first under Manchester coding,
+    /// or ends it with `Pulse::Stop` and returns its word. Bits beyond the
+    /// frame are kept count of and make the stop fail with `Error::Desync`.
+    /// Outside a frame, pulses are ignored.
+    pub fn push(&mut self, pulse: Pulse) -> Result<Option<u32>, Error> {
+        if !self.in_frame {
+            return Ok(None);
+        }
+        let bit = match pulse {
+            Pulse::Bit(bit) => bit,
+            Pulse::Stop => return self.finish().map(Some),
+        };
+
+        if !self.synced {
+            if bit {
+                self.in_frame = false;
+                return Err(Error::Framing);
+            }
+            self.synced = true;
+        } else if self.bits < self.expected {
+            self.data = self.data << 1 | bit as u32;
+            self.bits += 1;
+        } else if self.parity != Parity::None && self.parity_bit.is_none() {
+            self.parity_bit = Some(bit);
+        } else {
+            self.overlong = true;
+        }
+        return Ok(None);
+    }
+
+    /// Ends the frame as `Pulse::Stop` does, for receivers that know it is
+    /// over without seeing the idle line.
+    pub fn finish(&mut self) -> Result<u32, Error> {
+        self.in_frame = false;
+        // A low period released straight to idle carries no bits: that is
+        // a break.
+        if self.bits == 0 && self.parity_bit.is_none() {
+            return Err(Error::BreakReceived);
+        }
+        if self.overlong || self.bits != self.expected {
+            return Err(Error::Desync);
+        }
+        if self.parity_bit != self.parity.bit(self.data) {
+            return Err(Error::Parity);
+        }
+        return Ok(self.data);
+    }
+
+    /// Adds half a Manchester bit, sampled at level `high`, and returns the
+    /// bit once both halves are in; halves without a transition between
+    /// them are a framing error.
+    pub fn push_half(&mut self, high: bool) -> Result<Option<bool>, Error> {
+        if !self.in_frame {
+            return Ok(None);
+        }
+        let first = match self.half.take() {
+            Some(first) => first,
+            None => {
+                self.half = Some(high);
+                return Ok(None);
+            }
+        };
+        if first == high {
+            self.in_frame = false;
+            return Err(Error::Framing);
+        }
+
+        let sync = !self.synced;
+        self.push(Pulse::Bit(high))?;
+        if sync {
+            return Ok(None);
+        }
+        return Ok(Some(high));
+    }
+
+    /// Feeds the line at level `high` for `eighths` eighths of a phase and
+    /// returns the word once its frame is complete. The idle time after a
+    /// frame ends it, so feed it as soon as it is longer than
+    /// `PulseBands::stop`, or three Manchester halves. After an error, the
+    /// decoder waits for the next start condition.
+    pub fn feed(&mut self, high: bool, eighths: u32) -> Result<Option<u32>, Error> {
+        if !self.in_frame {
+            // A start condition shorter than half its length is a glitch.
+            if !high && eighths >= self.start / 2 {
+                self.begin();
+            }
+            return Ok(None);
+        }
+
+        if self.encoding == Encoding::Manchester {
+            return self.feed_halves(high, eighths);
+        }
+        if !high {
+            return Ok(None);
+        }
+        let pulse = match classify(&self.bands, eighths) {
+            Ok(pulse) => pulse,
+            Err(e) => {
+                self.in_frame = false;
+                return Err(e);
+            }
+        };
+        return self.push(pulse);
+    }
+
+    /// Splits a Manchester level into halves. Within a frame a level lasts
+    /// one or two halves; a longer high is the idle line.
+    fn feed_halves(&mut self, high: bool, eighths: u32) -> Result<Option<u32>, Error> {
+        let half = MANCHESTER_HALF as u32 * 8;
+        let halves = (eighths + half / 2) / half;
+        if halves == 0 || (halves > 2 && !high) {
+            self.in_frame = false;
+            return Err(Error::Framing);
+        }
+
+        if halves > 2 {
+            // The second half of a trailing one runs into the idle line.
+            if self.half.is_some() {
+                self.push_half(high)?;
+            }
+            return self.push(Pulse::Stop);
+        }
+        for _ in 0..halves {
+            self.push_half(high)?;
+        }
+        return Ok(None);
+    }
+}
@@ -0,0 +1,263 @@
+//! Runtime options of a wire. Changing them on one side only breaks the link,
+//! so both peers have to agree on every field.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    /// One sample at phase 3 of the bit.
+    Single,
+    /// Two samples half a phase either side of phase 3. If they disagree,
+    /// the pulse ended right at the decision point, e.g. because our edge
+    /// detection lagged, and the bit cannot be trusted: `Error::Marginal`.
+    /// Needs at least 2 ticks per phase.
+    Double,
+    /// The given number of reads one tick apart, centred on phase 3 and
+    /// decided by majority. Use an odd count, 3 or 5, to filter ringing on
+    /// slow edges; the reads have to fit in one phase, so at least as many
+    /// ticks per phase are needed.
+    Majority(u8),
+    /// No fixed sample point: every high pulse is timed in delay ticks and
+    /// classified by `Config::pulse_bands`. Tolerates peers whose clock is
+    /// off by 20% and more; use a `Timing` with several ticks per phase for
+    /// a fine enough measurement.
+    Measured,
+}
+
+/// Accepted high pulse widths for `Sampling::Measured`, in eighths of a
+/// phase. Widths outside both bands and below `stop` fail with
+/// `Error::InvalidPulse`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PulseBands {
+    /// Nominally 2 phases, 16.
+    pub zero: (u8, u8),
+    /// Nominally 4 phases, 32.
+    pub one: (u8, u8),
+    /// Longer pulses are the idle line after the last bit.
+    pub stop: u8,
+}
+
+impl Default for PulseBands {
+    fn default() -> Self {
+        PulseBands {
+            zero: (10, 23),
+            one: (24, 44),
+            stop: 48,
+        }
+    }
+}
+
+/// How bits are put on the line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Every bit is a high pulse of 4 phases for a one or 2 for a zero,
+    /// followed by low time up to 8 phases.
+    PulseWidth,
+    /// Every bit is a transition in its middle, see `manchester`.
+    Manchester,
+}
+
+/// Order of the data bits of every byte on the wire. Multi-byte words keep
+/// their bytes most significant first and reverse each byte on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    MsbFirst,
+    /// Least significant bit first, as UARTs send them.
+    LsbFirst,
+}
+
+impl BitOrder {
+    /// Turns a byte into the value whose bits, most significant first, are
+    /// in wire order; it also turns such a value back.
+    pub fn apply(self, byte: u8) -> u8 {
+        return match self {
+            BitOrder::MsbFirst => byte,
+            BitOrder::LsbFirst => byte.reverse_bits(),
+        };
+    }
+}
+
+/// Optional extra bit after the data bits of every frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    /// Makes the number of ones, parity bit included, even.
+    Even,
+    Odd,
+}
+
+impl Parity {
+    /// The parity bit to send after `data`, `None` without parity.
+    pub fn bit(self, data: u32) -> Option<bool> {
+        let odd = data.count_ones() % 2 == 1;
+        return match self {
+            Parity::None => None,
+            Parity::Even => Some(odd),
+            Parity::Odd => Some(!odd),
+        };
+    }
+
+    pub fn bits(self) -> u8 {
+        return match self {
+            Parity::None => 0,
+            _ => 1,
+        };
+    }
+}
+
+/// A timing profile: how many delays of the configured duration make up one
+/// phase. Slower profiles trade throughput for tolerance and power.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timing {
+    pub ticks_per_phase: u16,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing { ticks_per_phase: 1 }
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// May start transmissions.
+    Master = 0,
+    /// Only receives; `write` is refused until the role is set back. Acks
+    /// of received packets still go out.
+    Listener = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Line coding of the blocking transfers. Polled and captured receive
+    /// only support `Encoding::PulseWidth`.
+    pub encoding: Encoding,
+    /// Applied to every byte before the scrambler, so the scrambler always
+    /// runs in wire order.
+    pub bit_order: BitOrder,
+    pub sampling: Sampling,
+    pub pulse_bands: PulseBands,
+    /// Phases the line is held low before the first bit. Receivers accept a
+    /// frame once the line stayed low for half of it.
+    pub start_phases: u8,
+    /// Phases the line has to be idle before a transmission may start.
+    pub frame_gap: u8,
+    /// Consecutive samples a new line level has to persist before it counts
+    /// as an edge. 1 reacts to every change.
+    pub filter_depth: u8,
+    /// Drop to `Role::Listener` after this many consecutive writes found the
+    /// bus busy. The write that crosses the limit fails with `Error::Demoted`.
+    pub fallback_after: Option<u8>,
+    /// Extra attempts of a write that found the bus busy, each after a random
+    /// backoff; see `backoff`. Without it, the write fails right away.
+    pub csma: Option<u8>,
+    /// Whiten every byte with `Scrambler` before it goes on the wire.
+    pub scramble: bool,
+    pub parity: Parity,
+    /// Append and check checksums in checked transfers and packets. When
+    /// off, the `CrcEngine` passed to them is ignored.
+    pub crc: bool,
+    /// Largest packet payload accepted, to match the receive buffers.
+    pub max_payload: u8,
+    /// Retransmissions before a packet is given up.
+    pub retries: u8,
+    /// Phases to wait for a reply, or for the next byte of a frame, to start.
+    pub response_timeout: u16,
+    /// Phases to wait for the next edge inside a frame before giving up with
+    /// `Error::Truncated`; keep it above `BREAK_PHASES` if breaks are used.
+    /// Without it, edges are busy-polled. With it, the wait advances in delay
+    /// ticks, so both `Timing` profiles need at least `MIN_TIMEOUT_TICKS`
+    /// ticks per phase to keep edges precise.
+    pub bit_timeout: Option<u16>,
+    pub timing: Timing,
+    /// Profile used while the bus is in low-power mode.
+    pub low_power_timing: Timing,
+    /// Own device address on a multi-drop wire, see `addr`.
+    pub address: Option<u8>,
+    /// Group memberships, bit `n` for group address `0xf0 + n`.
+    pub groups: u16,
+    /// Only transmit while holding the token, see `token`.
+    pub token_passing: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            encoding: Encoding::PulseWidth,
+            bit_order: BitOrder::MsbFirst,
+            sampling: Sampling::Single,
+            pulse_bands: PulseBands::default(),
+            start_phases: 4,
+            frame_gap: 4,
+            filter_depth: 1,
+            fallback_after: None,
+            csma: None,
+            scramble: false,
+            parity: Parity::None,
+            crc: true,
+            max_payload: u8::MAX,
+            retries: 3,
+            response_timeout: 200,
+            bit_timeout: None,
+            timing: Timing::default(),
+            low_power_timing: Timing { ticks_per_phase: 8 },
+            address: None,
+            groups: 0,
+            token_passing: false,
+        }
+    }
+}
+
+/// Reasons for `Config::validate` to reject a configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A phase of zero length, from the delay or a timing profile.
+    ZeroPhase,
+    /// `start_phases` too short for receivers to tell a frame from a glitch.
+    StartTooShort,
+    /// `Sampling::Majority` needs at least one read.
+    NoSamples,
+    /// The own address is the broadcast or a group address.
+    ReservedAddress,
+    /// A `Timing` profile with too few ticks per phase for `bit_timeout` or
+    /// the chosen `sampling`.
+    CoarseTiming,
+}
+
+/// Ticks per phase `Config::bit_timeout` needs: edges are seen up to a
+/// tick late, a quarter phase at most.
+pub const MIN_TIMEOUT_TICKS: u16 = 4;
+
+impl Config {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.timing.ticks_per_phase == 0 || self.low_power_timing.ticks_per_phase == 0 {
+            return Err(ConfigError::ZeroPhase);
+        }
+        if self.start_phases < 2 {
+            return Err(ConfigError::StartTooShort);
+        }
+        if self.sampling == Sampling::Majority(0) {
+            return Err(ConfigError::NoSamples);
+        }
+        let ticks = self
+            .timing
+            .ticks_per_phase
+            .min(self.low_power_timing.ticks_per_phase);
+        if self.bit_timeout.is_some() && ticks < MIN_TIMEOUT_TICKS {
+            return Err(ConfigError::CoarseTiming);
+        }
+        let needed = match self.sampling {
+            Sampling::Double => 2,
+            Sampling::Majority(n) => n as u16,
+            _ => 1,
+        };
+        if ticks < needed {
+            return Err(ConfigError::CoarseTiming);
+        }
+        if let Some(addr) = self.address {
+            if crate::addr::is_multicast(addr) {
+                return Err(ConfigError::ReservedAddress);
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,83 @@
+//! Bus management commands from the master, carried in `KIND_CONTROL`
+//! packets. Receivers apply them inside `recv_packet`.
+use crate::crc::CrcEngine;
+use crate::observer::WireObserver;
+use crate::packet::{write_packet, KIND_CONTROL};
+use crate::token::Token;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Switch to `Config::low_power_timing` after this packet.
+pub const ENTER_LOW_POWER: u8 = 0x01;
+/// Switch back to `Config::timing` after this packet.
+pub const EXIT_LOW_POWER: u8 = 0x02;
+/// Asks the holder for the token, see `token`.
+pub const TOKEN_REQUEST: u8 = 0x03;
+/// Hands the token to the receiver.
+pub const TOKEN_GRANT: u8 = 0x04;
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub(crate) fn send_control<C: CrcEngine>(
+        &mut self,
+        command: u8,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return write_packet(self, [KIND_CONTROL, 0, 1], &[command], crc, delay);
+    }
+
+    pub(crate) fn apply_control(&mut self, command: u8) {
+        match command {
+            ENTER_LOW_POWER => self.set_low_power(true),
+            EXIT_LOW_POWER => self.set_low_power(false),
+            TOKEN_REQUEST => self.token_requested = true,
+            TOKEN_GRANT => self.token = Token::Held,
+            _ => {}
+        }
+    }
+
+    pub fn is_low_power(&self) -> bool {
+        return self.low_power;
+    }
+
+    /// Switches the local timing profile without telling anyone.
+    pub fn set_low_power(&mut self, low_power: bool) {
+        self.low_power = low_power;
+        self.timing = if low_power {
+            self.config.low_power_timing
+        } else {
+            self.config.timing
+        };
+    }
+
+    /// Commands every slave into low-power mode, then follows itself.
+    pub fn enter_low_power<C: CrcEngine>(
+        &mut self,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        self.send_control(ENTER_LOW_POWER, crc, delay)?;
+        self.set_low_power(true);
+        return Ok(());
+    }
+
+    /// Sent with the low-power profile, which is what the slaves listen with.
+    pub fn exit_low_power<C: CrcEngine>(
+        &mut self,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        self.send_control(EXIT_LOW_POWER, crc, delay)?;
+        self.set_low_power(false);
+        return Ok(());
+    }
+}
@@ -0,0 +1,169 @@
+//! Frame checksums. `CrcEngine` is the hook for MCU CRC peripherals; `Crc16`
+//! is the software fallback.
+use crate::config::Config;
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire, ReadWrite};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub trait CrcEngine {
+    /// Number of checksum bytes appended to a frame, at most 4.
+    const WIDTH: usize;
+    /// Identifies the algorithm in the wire format descriptor.
+    const ID: u8;
+
+    fn reset(&mut self);
+    fn update(&mut self, data: &[u8]);
+    /// Checksum of everything fed since the last `reset`, right-aligned.
+    fn finish(&mut self) -> u32;
+}
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc16 {
+    crc: u16,
+}
+
+impl Crc16 {
+    pub const fn new() -> Self {
+        Crc16 { crc: 0xffff }
+    }
+}
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrcEngine for Crc16 {
+    const WIDTH: usize = 2;
+    const ID: u8 = 1;
+
+    fn reset(&mut self) {
+        self.crc = 0xffff;
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.crc ^= (byte as u16) << 8;
+            for _ in 0..8 {
+                if self.crc & 0x8000 != 0 {
+                    self.crc = self.crc << 1 ^ 0x1021;
+                } else {
+                    self.crc <<= 1;
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) -> u32 {
+        return self.crc as u32;
+    }
+}
+
+/// No checksum at all, for links that do without one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoCrc;
+
+impl CrcEngine for NoCrc {
+    const WIDTH: usize = 0;
+    const ID: u8 = 0;
+
+    fn reset(&mut self) {}
+
+    fn update(&mut self, _data: &[u8]) {}
+
+    fn finish(&mut self) -> u32 {
+        return 0;
+    }
+}
+
+pub(crate) fn checksum<C: CrcEngine>(crc: &mut C, data: &[u8]) -> u32 {
+    crc.reset();
+    crc.update(data);
+    return crc.finish();
+}
+
+/// Checksum bytes per frame: `C::WIDTH`, or none with `Config::crc` off.
+pub(crate) fn crc_width<C: CrcEngine>(config: &Config) -> usize {
+    if config.crc {
+        return C::WIDTH;
+    }
+    return 0;
+}
+
+/// Checksummed transfers over any byte transport.
+pub trait CheckedTransport<T>: ReadWrite<T> {
+    /// Sends `data` followed by its checksum, most significant byte first.
+    fn write_checked<C: CrcEngine>(
+        &mut self,
+        data: &[u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let sum = checksum(crc, data);
+
+        for &byte in data {
+            self.write(byte, delay)?;
+        }
+        for i in (0..crc_width::<C>(&self.config())).rev() {
+            self.write((sum >> (8 * i)) as u8, delay)?;
+        }
+
+        return Ok(());
+    }
+
+    /// Fills `buf` and verifies the checksum that follows it.
+    fn read_checked<C: CrcEngine>(
+        &mut self,
+        buf: &mut [u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        for byte in buf.iter_mut() {
+            *byte = self.read(delay)?;
+        }
+
+        let width = crc_width::<C>(&self.config());
+        let mut sum = 0u32;
+        for _ in 0..width {
+            sum = sum << 8 | self.read(delay)? as u32;
+        }
+
+        if width > 0 && sum != checksum(crc, buf) {
+            return self.settle(Err(Error::Crc));
+        }
+        return Ok(());
+    }
+}
+
+impl<T, W: ReadWrite<T>> CheckedTransport<T> for W {}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn write_checked<C: CrcEngine>(
+        &mut self,
+        data: &[u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return CheckedTransport::write_checked(self, data, crc, delay);
+    }
+
+    pub fn read_checked<C: CrcEngine>(
+        &mut self,
+        buf: &mut [u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return CheckedTransport::read_checked(self, buf, crc, delay);
+    }
+}
@@ -0,0 +1,115 @@
+//! Compact, comparable description of the wire format a driver speaks.
+//! Peers exchange it when connecting; two builds that would garble each
+//! other's frames produce different descriptors.
+//!
+//! Layout, all multi-byte fields big-endian:
+//!
+//! | byte | field                         |
+//! |------|-------------------------------|
+//! | 0    | descriptor layout version     |
+//! | 1-2  | `WIRE_FORMAT_VERSION`         |
+//! | 3-6  | phase duration in µs          |
+//! | 7    | line coding, 0 = pulse width, |
+//! |      | 1 = Manchester, plus 0x80 for |
+//! |      | LSB first                     |
+//! | 8    | start condition in phases     |
+//! | 9    | `CrcEngine::ID`               |
+//! | 10   | `CrcEngine::WIDTH`            |
+//! | 11   | parity, 0 = none, 1 = even,   |
+//! |      | 2 = odd                       |
+//! | 12   | frame gap in phases           |
+//! | 13   | flags, 0x01 = scrambled       |
+use crate::config::{BitOrder, Encoding, Parity};
+use crate::crc::CrcEngine;
+use crate::observer::WireObserver;
+use crate::units::Unit;
+use crate::{compatible, Error, HalfDuplexWire, WIRE_FORMAT_VERSION};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const DESCRIPTOR_LEN: usize = 14;
+const LAYOUT_VERSION: u8 = 3;
+const CODING_PULSE_WIDTH: u8 = 0;
+const CODING_MANCHESTER: u8 = 1;
+const CODING_LSB_FIRST: u8 = 0x80;
+const FLAG_SCRAMBLED: u8 = 0x01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Descriptor([u8; DESCRIPTOR_LEN]);
+
+impl Descriptor {
+    pub fn from_bytes(bytes: [u8; DESCRIPTOR_LEN]) -> Self {
+        Descriptor(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; DESCRIPTOR_LEN] {
+        return &self.0;
+    }
+
+    pub fn wire_format_version(&self) -> u16 {
+        return u16::from_be_bytes([self.0[1], self.0[2]]);
+    }
+
+    pub fn phase_micros(&self) -> u32 {
+        return u32::from_be_bytes([self.0[3], self.0[4], self.0[5], self.0[6]]);
+    }
+
+    /// Fails with `Error::IncompatiblePeer` unless both sides speak
+    /// compatible wire format versions with identical settings.
+    pub fn check(&self, peer: &Descriptor) -> Result<(), Error> {
+        if self.0[0] != peer.0[0]
+            || !compatible(self.wire_format_version(), peer.wire_format_version())
+            || self.0[3..] != peer.0[3..]
+        {
+            return Err(Error::IncompatiblePeer);
+        }
+        return Ok(());
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Unit,
+    V: WireObserver,
+{
+    /// Describes the active configuration, with `C` as the frame checksum
+    /// unless `Config::crc` is off.
+    pub fn descriptor<C: CrcEngine>(&self) -> Descriptor {
+        let mut bytes = [0u8; DESCRIPTOR_LEN];
+
+        let phase = self
+            .delay
+            .as_micros()
+            .saturating_mul(self.timing.ticks_per_phase as u32);
+
+        bytes[0] = LAYOUT_VERSION;
+        bytes[1..3].copy_from_slice(&WIRE_FORMAT_VERSION.to_be_bytes());
+        bytes[3..7].copy_from_slice(&phase.to_be_bytes());
+        bytes[7] = match self.config.encoding {
+            Encoding::PulseWidth => CODING_PULSE_WIDTH,
+            Encoding::Manchester => CODING_MANCHESTER,
+        };
+        if self.config.bit_order == BitOrder::LsbFirst {
+            bytes[7] |= CODING_LSB_FIRST;
+        }
+        bytes[8] = self.config.start_phases;
+        if self.config.crc {
+            bytes[9] = C::ID;
+            bytes[10] = C::WIDTH as u8;
+        }
+        bytes[11] = match self.config.parity {
+            Parity::None => 0,
+            Parity::Even => 1,
+            Parity::Odd => 2,
+        };
+        bytes[12] = self.config.frame_gap;
+        if self.config.scramble {
+            bytes[13] |= FLAG_SCRAMBLED;
+        }
+
+        return Descriptor(bytes);
+    }
+}
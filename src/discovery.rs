@@ -0,0 +1,114 @@
+//! Time-slotted enumeration of the devices on a multi-drop wire.
+//!
+//! The master sends `DISCOVER`, the first address and the number of slots.
+//! The device with address `first + k` answers with its own address `k`
+//! slots after the command, so no two answers overlap.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const DISCOVER: u8 = 0xd5;
+/// Length of one answer slot, one frame plus some margin.
+pub const SLOT_PHASES: u16 = 96;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AddressSet {
+    bits: [u8; 32],
+}
+
+impl AddressSet {
+    pub const fn new() -> Self {
+        AddressSet { bits: [0u8; 32] }
+    }
+
+    pub fn insert(&mut self, addr: u8) {
+        self.bits[(addr / 8) as usize] |= 1 << (addr % 8);
+    }
+
+    pub fn contains(&self, addr: u8) -> bool {
+        return self.bits[(addr / 8) as usize] & 1 << (addr % 8) != 0;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.bits.iter().map(|b| b.count_ones() as usize).sum();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.bits.iter().all(|&b| b == 0);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        return (0..=255u8).filter(move |&a| self.contains(a));
+    }
+
+    /// Copies the addresses into a `heapless::Vec`, dropping those that do
+    /// not fit.
+    #[cfg(feature = "heapless")]
+    pub fn to_vec<const N: usize>(&self) -> heapless::Vec<u8, N> {
+        let mut vec = heapless::Vec::new();
+        for addr in self.iter() {
+            if vec.push(addr).is_err() {
+                break;
+            }
+        }
+        return vec;
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Asks every device in `first..first + count` to report.
+    pub fn scan(
+        &mut self,
+        first: u8,
+        count: u8,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<AddressSet, Error> {
+        self.write(DISCOVER, delay)?;
+        self.write(first, delay)?;
+        self.write(count, delay)?;
+
+        let mut found = AddressSet::new();
+        // One extra slot absorbs the drift of waiting out empty slots.
+        for _ in 0..=count {
+            match self.read_timeout(SLOT_PHASES, delay) {
+                Ok(addr) => found.insert(addr),
+                Err(Error::Timeout) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        return Ok(found);
+    }
+
+    /// Device side of `scan`, to be called right after `DISCOVER` was read.
+    pub fn answer_discovery(
+        &mut self,
+        addr: u8,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let first = self.read(delay)?;
+        let count = self.read(delay)?;
+
+        let slot = addr.wrapping_sub(first);
+        if addr < first || slot >= count {
+            return Ok(());
+        }
+
+        for _ in 0..slot {
+            for _ in 0..SLOT_PHASES {
+                self.phase(delay);
+            }
+        }
+
+        return self.write(addr, delay);
+    }
+}
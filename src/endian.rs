@@ -0,0 +1,106 @@
+//! Byte-order aware transfer of multi-byte primitives.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire, BUF_SIZE};
+use core::mem::size_of;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub trait WireValue: Copy {
+    const SIZE: usize;
+    fn from_le(buf: &[u8]) -> Self;
+    fn from_be(buf: &[u8]) -> Self;
+    fn to_le(self, buf: &mut [u8]);
+    fn to_be(self, buf: &mut [u8]);
+}
+
+macro_rules! wire_value {
+    ( $( $t : ty ),* ) => {
+        $(
+            impl WireValue for $t {
+                const SIZE: usize = size_of::<$t>();
+
+                fn from_le(buf: &[u8]) -> Self {
+                    let mut bytes = [0u8; size_of::<$t>()];
+                    bytes.copy_from_slice(&buf[..Self::SIZE]);
+                    return <$t>::from_le_bytes(bytes);
+                }
+
+                fn from_be(buf: &[u8]) -> Self {
+                    let mut bytes = [0u8; size_of::<$t>()];
+                    bytes.copy_from_slice(&buf[..Self::SIZE]);
+                    return <$t>::from_be_bytes(bytes);
+                }
+
+                fn to_le(self, buf: &mut [u8]) {
+                    buf[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn to_be(self, buf: &mut [u8]) {
+                    buf[..Self::SIZE].copy_from_slice(&self.to_be_bytes());
+                }
+            }
+        )*
+    };
+}
+
+wire_value!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    fn get_bytes<U: WireValue>(
+        &mut self,
+        buf: &mut [u8; BUF_SIZE],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        for byte in buf.iter_mut().take(U::SIZE) {
+            *byte = self.read(delay)?;
+        }
+        return Ok(());
+    }
+
+    fn put_bytes(&mut self, buf: &[u8], delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        for &byte in buf {
+            self.write(byte, delay)?;
+        }
+        return Ok(());
+    }
+
+    pub fn get_le<U: WireValue>(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<U, Error> {
+        let mut buf = [0u8; BUF_SIZE];
+        self.get_bytes::<U>(&mut buf, delay)?;
+        return Ok(U::from_le(&buf));
+    }
+
+    pub fn get_be<U: WireValue>(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<U, Error> {
+        let mut buf = [0u8; BUF_SIZE];
+        self.get_bytes::<U>(&mut buf, delay)?;
+        return Ok(U::from_be(&buf));
+    }
+
+    pub fn put_le<U: WireValue>(
+        &mut self,
+        value: U,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let mut buf = [0u8; BUF_SIZE];
+        value.to_le(&mut buf);
+        return self.put_bytes(&buf[..U::SIZE], delay);
+    }
+
+    pub fn put_be<U: WireValue>(
+        &mut self,
+        value: U,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let mut buf = [0u8; BUF_SIZE];
+        value.to_be(&mut buf);
+        return self.put_bytes(&buf[..U::SIZE], delay);
+    }
+}
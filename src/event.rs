@@ -0,0 +1,90 @@
+//! Events as data: an observer that queues what happened, for applications
+//! that would rather poll than run callbacks in the middle of a transfer.
+use crate::observer::{Direction, WireObserver};
+use crate::ring::Ring;
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireEvent {
+    FrameSent {
+        len: usize,
+    },
+    FrameReceived {
+        len: usize,
+    },
+    /// A transmission found the line busy.
+    CollisionDetected,
+    /// The peer did not answer in time.
+    LinkLost,
+    BreakReceived,
+    Error(Error),
+}
+
+impl From<Error> for WireEvent {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Busy => WireEvent::CollisionDetected,
+            Error::Timeout | Error::NoResponse => WireEvent::LinkLost,
+            Error::BreakReceived => WireEvent::BreakReceived,
+            e => WireEvent::Error(e),
+        }
+    }
+}
+
+/// Holds up to `N` events; while it is full, new ones are dropped and counted.
+pub struct EventQueue<const N: usize> {
+    events: Ring<WireEvent, N>,
+    dropped: u32,
+}
+
+impl<const N: usize> EventQueue<N> {
+    pub const fn new() -> Self {
+        EventQueue {
+            events: Ring::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: WireEvent) {
+        if self.events.push(event).is_err() {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<WireEvent> {
+        return self.events.pop();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.events.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.events.len() == 0;
+    }
+
+    /// Events lost to a full queue since it was created.
+    pub fn dropped(&self) -> u32 {
+        return self.dropped;
+    }
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> WireObserver for EventQueue<N> {
+    fn on_frame(&mut self, dir: Direction, bytes: &[u8]) {
+        let len = bytes.len();
+        self.push(match dir {
+            Direction::Tx => WireEvent::FrameSent { len: len },
+            Direction::Rx => WireEvent::FrameReceived { len: len },
+        });
+    }
+
+    fn on_error(&mut self, e: Error) {
+        self.push(e.into());
+    }
+}
@@ -0,0 +1,70 @@
+//! Variable-length frames: a length byte, then that many payload bytes. The
+//! receiver checks the length against its buffer before accepting anything.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire, ReadWrite};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Frames over any byte transport.
+pub trait FrameTransport<T>: ReadWrite<T> {
+    /// Sends `data`, at most 255 bytes, behind its length.
+    fn send_frame(&mut self, data: &[u8], delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        if data.len() > u8::MAX as usize {
+            return Err(Error::FrameTooLarge);
+        }
+
+        self.write(data.len() as u8, delay)?;
+        for &byte in data {
+            self.write(byte, delay)?;
+        }
+        return Ok(());
+    }
+
+    /// Receives a frame into `buf` and returns its length. A frame longer
+    /// than `buf` is read to its end and dropped with `Error::FrameTooLarge`,
+    /// so the next frame starts in sync.
+    fn recv_frame(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<usize, Error> {
+        let timeout = self.config().response_timeout;
+        let len = self.read(delay)? as usize;
+
+        if len > buf.len() {
+            for _ in 0..len {
+                self.read_timeout(timeout, delay)?;
+            }
+            return self.settle(Err(Error::FrameTooLarge));
+        }
+
+        for byte in buf[..len].iter_mut() {
+            *byte = self.read_timeout(timeout, delay)?;
+        }
+        return Ok(len);
+    }
+}
+
+impl<T, W: ReadWrite<T>> FrameTransport<T> for W {}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn send_frame(&mut self, data: &[u8], delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return FrameTransport::send_frame(self, data, delay);
+    }
+
+    pub fn recv_frame(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<usize, Error> {
+        return FrameTransport::recv_frame(self, buf, delay);
+    }
+}
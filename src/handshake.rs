@@ -0,0 +1,224 @@
+//! Link bring-up: both sides swap their `Descriptor` as a checksummed frame
+//! and refuse to continue with a peer that speaks a different format.
+//!
+//! `hello` is the lenient alternative for mixed firmware: both sides swap
+//! their `Capabilities` and continue with what both support.
+//!
+//! ```text
+//! | HELLO | version | flags | max frame |
+//! ```
+//!
+//! The hello is sent without checksum and in pulse-width coding, since the
+//! peer may support neither checksums nor Manchester; only the protocol
+//! version, the major `WIRE_FORMAT_VERSION`, has to match.
+use crate::config::Encoding;
+use crate::crc::CrcEngine;
+use crate::descriptor::{Descriptor, DESCRIPTOR_LEN};
+use crate::observer::WireObserver;
+use crate::units::{PhaseDelay, Unit};
+use crate::{Error, HalfDuplexWire, WIRE_FORMAT_VERSION};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const HELLO: u8 = 0x48;
+pub const HELLO_LEN: usize = 4;
+/// Checksums in checked transfers and packets, see `Config::crc`.
+pub const CAP_CRC: u8 = 0x01;
+/// Manchester coding, offered while `Config::encoding` asks for it.
+pub const CAP_MANCHESTER: u8 = 0x02;
+/// Sealed frames, see `secure`.
+pub const CAP_SECURE: u8 = 0x04;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    pub version: u8,
+    /// `CAP_*` bits.
+    pub flags: u8,
+    /// Largest packet payload accepted.
+    pub max_frame: u8,
+}
+
+impl Capabilities {
+    pub fn to_bytes(&self) -> [u8; HELLO_LEN] {
+        return [HELLO, self.version, self.flags, self.max_frame];
+    }
+
+    pub fn from_bytes(bytes: [u8; HELLO_LEN]) -> Result<Self, Error> {
+        if bytes[0] != HELLO {
+            return Err(Error::Framing);
+        }
+        return Ok(Capabilities {
+            version: bytes[1],
+            flags: bytes[2],
+            max_frame: bytes[3],
+        });
+    }
+
+    /// What both sides support; the versions have to match.
+    pub fn common(&self, peer: &Capabilities) -> Result<Capabilities, Error> {
+        if self.version != peer.version {
+            return Err(Error::IncompatiblePeer);
+        }
+        return Ok(Capabilities {
+            version: self.version,
+            flags: self.flags & peer.flags,
+            max_frame: self.max_frame.min(peer.max_frame),
+        });
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Unit,
+    V: WireObserver,
+{
+    fn read_descriptor<C: CrcEngine>(
+        &mut self,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Descriptor, Error> {
+        let mut bytes = [0u8; DESCRIPTOR_LEN];
+        self.read_checked(&mut bytes, crc, delay)?;
+        return Ok(Descriptor::from_bytes(bytes));
+    }
+
+    /// Initiating side: sends our descriptor, then checks the peer's answer.
+    pub fn connect<C: CrcEngine>(
+        &mut self,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Descriptor, Error> {
+        let own = self.descriptor::<C>();
+        self.write_checked(own.as_bytes(), crc, delay)?;
+
+        let peer = self.read_descriptor(crc, delay)?;
+        if let Err(e) = own.check(&peer) {
+            return self.settle(Err(e));
+        }
+        return Ok(peer);
+    }
+
+    /// Answering side of `connect`. The peer gets our descriptor even when
+    /// it does not match, so both ends report the mismatch.
+    pub fn accept<C: CrcEngine>(
+        &mut self,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Descriptor, Error> {
+        let peer = self.read_descriptor(crc, delay)?;
+
+        let own = self.descriptor::<C>();
+        self.write_checked(own.as_bytes(), crc, delay)?;
+
+        if let Err(e) = own.check(&peer) {
+            return self.settle(Err(e));
+        }
+        return Ok(peer);
+    }
+
+    /// What this driver offers in `hello`, given the active configuration.
+    pub fn capabilities(&self) -> Capabilities {
+        let mut flags = 0;
+        if self.config.encoding == Encoding::Manchester {
+            flags |= CAP_MANCHESTER;
+        }
+        if self.config.crc {
+            flags |= CAP_CRC;
+        }
+        if cfg!(feature = "secure") {
+            flags |= CAP_SECURE;
+        }
+        return Capabilities {
+            version: (WIRE_FORMAT_VERSION >> 8) as u8,
+            flags: flags,
+            max_frame: self.config.max_payload,
+        };
+    }
+
+    /// Capabilities the peer announced in the last hello.
+    pub fn peer_capabilities(&self) -> Option<Capabilities> {
+        return self.peer_caps;
+    }
+
+    fn read_hello(
+        &mut self,
+        first: Option<u16>,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Capabilities, Error> {
+        let timeout = self.config.response_timeout;
+        let mut bytes = [0u8; HELLO_LEN];
+        bytes[0] = match first {
+            Some(t) => self.read_timeout(t, delay)?,
+            None => self.read(delay)?,
+        };
+        for byte in bytes.iter_mut().skip(1) {
+            *byte = self.read_timeout(timeout, delay)?;
+        }
+        return Capabilities::from_bytes(bytes);
+    }
+
+    fn write_hello(
+        &mut self,
+        own: &Capabilities,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        for byte in own.to_bytes() {
+            self.write(byte, delay)?;
+        }
+        return Ok(());
+    }
+
+    /// Runs `f` in pulse-width coding, which every version speaks.
+    fn in_base_coding<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let encoding = self.config.encoding;
+        self.config.encoding = Encoding::PulseWidth;
+        let result = f(self);
+        self.config.encoding = encoding;
+        return result;
+    }
+
+    /// Switches to what both sides support: checksums only if both have them,
+    /// Manchester only if both ask for it, payloads no longer than either
+    /// accepts.
+    fn negotiate(&mut self, own: Capabilities, peer: Capabilities) -> Result<Capabilities, Error> {
+        self.peer_caps = Some(peer);
+        let common = match own.common(&peer) {
+            Ok(c) => c,
+            Err(e) => return self.settle(Err(e)),
+        };
+        self.config.crc = common.flags & CAP_CRC != 0;
+        self.config.encoding = if common.flags & CAP_MANCHESTER != 0 {
+            Encoding::Manchester
+        } else {
+            Encoding::PulseWidth
+        };
+        self.config.max_payload = common.max_frame;
+        return Ok(common);
+    }
+
+    /// Initiating side: announces our capabilities, waits for the peer's and
+    /// adopts the common set, which it returns.
+    pub fn hello(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<Capabilities, Error> {
+        let own = self.capabilities();
+        let timeout = self.config.response_timeout;
+        let peer = self.in_base_coding(|wire| {
+            wire.write_hello(&own, delay)?;
+            return wire.read_hello(Some(timeout), delay);
+        })?;
+        return self.negotiate(own, peer);
+    }
+
+    /// Answering side of `hello`.
+    pub fn hello_reply(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<Capabilities, Error> {
+        let own = self.capabilities();
+        let peer = self.in_base_coding(|wire| {
+            let peer = wire.read_hello(None, delay)?;
+            wire.write_hello(&own, delay)?;
+            return Ok(peer);
+        })?;
+        return self.negotiate(own, peer);
+    }
+}
@@ -0,0 +1,25 @@
+//! Sleeping while the receiver waits for the line to change. By default the
+//! receive loops spin; an `IdleWait` lets them stop the CPU instead, e.g.
+//!
+//! ```ignore
+//! struct Wfi;
+//!
+//! impl IdleWait for Wfi {
+//!     fn wait(&mut self) {
+//!         cortex_m::asm::wfi();
+//!     }
+//! }
+//! ```
+//!
+//! with an EXTI interrupt on both edges of the wire pin to wake up again.
+//! `wait` may also return early; the receiver just checks the line again.
+
+pub trait IdleWait {
+    /// Called between two polls of an unchanged line.
+    fn wait(&mut self);
+}
+
+/// Busy-waits.
+impl IdleWait for () {
+    fn wait(&mut self) {}
+}
@@ -0,0 +1,134 @@
+//! Transfers larger than one packet, e.g. firmware images, split into
+//! chunks that travel as acked packets:
+//!
+//! ```text
+//! | total (2 bytes) | offset (2 bytes) | data |
+//! ```
+//!
+//! Both fields are big-endian; `total` is the length of the whole transfer,
+//! at most 64 KiB. A chunk that runs out of packet retries is tried again
+//! from the same offset, so a flaky line only costs the chunk it hit, not
+//! the transfer.
+use crate::crc::CrcEngine;
+use crate::observer::WireObserver;
+use crate::packet::recv_packet_accepting;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const CHUNK_HEADER_LEN: usize = 4;
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Sends `data` in chunks of up to `chunk_size` bytes, fewer if
+    /// `Config::max_payload` asks for it. `on_progress` gets the bytes
+    /// acked so far and the total after every chunk. A chunk that fails is
+    /// resent up to `Config::retries` times before the transfer is given up.
+    pub fn send_large<C: CrcEngine>(
+        &mut self,
+        data: &[u8],
+        chunk_size: u8,
+        crc: &mut C,
+        mut on_progress: impl FnMut(usize, usize),
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        if data.len() > u16::MAX as usize {
+            return Err(Error::FrameTooLarge);
+        }
+        let room = (self.config.max_payload as usize).saturating_sub(CHUNK_HEADER_LEN);
+        let chunk_size = room.min(chunk_size as usize);
+        if chunk_size == 0 {
+            return Err(Error::Unavailable);
+        }
+
+        let total = (data.len() as u16).to_be_bytes();
+        let mut payload = [0u8; u8::MAX as usize];
+        let mut offset = 0;
+        let mut failures = 0;
+
+        loop {
+            let end = data.len().min(offset + chunk_size);
+            let len = CHUNK_HEADER_LEN + end - offset;
+            payload[..2].copy_from_slice(&total);
+            payload[2..4].copy_from_slice(&(offset as u16).to_be_bytes());
+            payload[CHUNK_HEADER_LEN..len].copy_from_slice(&data[offset..end]);
+
+            match self.send_packet(&payload[..len], crc, delay) {
+                Ok(()) => {
+                    failures = 0;
+                    offset = end;
+                    on_progress(offset, data.len());
+                    if offset == data.len() {
+                        return Ok(());
+                    }
+                }
+                Err(Error::NoResponse | Error::Busy) if failures < self.config.retries => {
+                    failures += 1;
+                    self.wait_retry_gap(delay);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Receives a transfer of `send_large` into `buf` and returns its
+    /// length. `on_progress` gets the bytes received so far and the total
+    /// after every new chunk. Damaged chunks are dropped and wait for their
+    /// retransmission; once the first chunk is in, the next one has to start
+    /// within the time the sender may spend retrying. A transfer larger than
+    /// `buf` is refused with a nak at its first chunk and fails with
+    /// `Error::FrameTooLarge`, before anything is acked.
+    pub fn recv_large<C: CrcEngine>(
+        &mut self,
+        buf: &mut [u8],
+        crc: &mut C,
+        mut on_progress: impl FnMut(usize, usize),
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<usize, Error> {
+        let mut chunk = [0u8; u8::MAX as usize];
+        let mut received = 0;
+        let mut first = None;
+        let attempts = (self.config.retries as u16 + 1) * (self.config.retries as u16 + 2);
+        let patience = self.config.response_timeout.saturating_mul(attempts);
+
+        loop {
+            let fits = |payload: &[u8]| {
+                payload.len() < CHUNK_HEADER_LEN
+                    || u16::from_be_bytes([payload[0], payload[1]]) as usize <= buf.len()
+            };
+            let packet = match recv_packet_accepting(self, first, &mut chunk, crc, fits, delay) {
+                Ok(p) => p,
+                Err(Error::Crc | Error::Framing | Error::Parity) => continue,
+                Err(e) => return Err(e),
+            };
+            let payload = packet.payload;
+            if payload.len() < CHUNK_HEADER_LEN {
+                continue;
+            }
+
+            let total = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+            let offset = u16::from_be_bytes([payload[2], payload[3]]) as usize;
+            let data = &payload[CHUNK_HEADER_LEN..];
+            // Chunks other than the next one, e.g. of an earlier transfer,
+            // are acked but not used.
+            if offset != received || offset + data.len() > total {
+                continue;
+            }
+
+            buf[offset..offset + data.len()].copy_from_slice(data);
+            received += data.len();
+            first = Some(patience);
+            on_progress(received, total);
+            if received == total {
+                return Ok(total);
+            }
+        }
+    }
+}
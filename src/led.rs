@@ -0,0 +1,115 @@
+//! Bus activity LEDs driven from the observer hook. Every event lights the
+//! LED; `tick` switches it off again once it has been on for at least the
+//! configured time, so even a single short frame produces a visible blink.
+use crate::observer::{Direction, WireObserver};
+use crate::Error;
+use embedded_hal::digital::v2::OutputPin;
+
+pub struct ActivityLed<P> {
+    pin: P,
+    min_on: u32,
+    lit_at: Option<u32>,
+    triggered: bool,
+}
+
+impl<P> ActivityLed<P>
+where
+    P: OutputPin,
+{
+    /// `min_on` is counted in the units of the clock passed to `tick`.
+    pub fn new(mut pin: P, min_on: u32) -> Self {
+        pin.set_low().ok();
+        ActivityLed {
+            pin: pin,
+            min_on: min_on,
+            lit_at: None,
+            triggered: false,
+        }
+    }
+
+    pub fn trigger(&mut self) {
+        self.pin.set_high().ok();
+        self.triggered = true;
+    }
+
+    /// Call regularly with the current time, e.g. from the main loop.
+    pub fn tick(&mut self, now: u32) {
+        if self.triggered {
+            // The on-time starts at the first tick after the latest event.
+            self.triggered = false;
+            self.lit_at = Some(now);
+            return;
+        }
+
+        if let Some(at) = self.lit_at {
+            if now.wrapping_sub(at) >= self.min_on {
+                self.pin.set_low().ok();
+                self.lit_at = None;
+            }
+        }
+    }
+
+    pub fn release(self) -> P {
+        return self.pin;
+    }
+}
+
+/// A single LED showing any activity, errors included.
+impl<P> WireObserver for ActivityLed<P>
+where
+    P: OutputPin,
+{
+    fn on_frame(&mut self, _dir: Direction, _bytes: &[u8]) {
+        self.trigger();
+    }
+
+    fn on_error(&mut self, _e: Error) {
+        self.trigger();
+    }
+}
+
+/// Separate LEDs for transmit, receive and errors.
+pub struct ActivityLeds<TX, RX, ERR> {
+    pub tx: ActivityLed<TX>,
+    pub rx: ActivityLed<RX>,
+    pub error: ActivityLed<ERR>,
+}
+
+impl<TX, RX, ERR> ActivityLeds<TX, RX, ERR>
+where
+    TX: OutputPin,
+    RX: OutputPin,
+    ERR: OutputPin,
+{
+    pub fn new(tx: ActivityLed<TX>, rx: ActivityLed<RX>, error: ActivityLed<ERR>) -> Self {
+        ActivityLeds {
+            tx: tx,
+            rx: rx,
+            error: error,
+        }
+    }
+
+    pub fn tick(&mut self, now: u32) {
+        self.tx.tick(now);
+        self.rx.tick(now);
+        self.error.tick(now);
+    }
+}
+
+impl<TX, RX, ERR> WireObserver for ActivityLeds<TX, RX, ERR>
+where
+    TX: OutputPin,
+    RX: OutputPin,
+    ERR: OutputPin,
+{
+    fn on_frame(&mut self, dir: Direction, _bytes: &[u8]) {
+        match dir {
+            Direction::Tx => self.tx.trigger(),
+            Direction::Rx => self.rx.trigger(),
+        }
+    }
+
+    fn on_error(&mut self, _e: Error) {
+        self.error.trigger();
+    }
+}
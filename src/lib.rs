@@ -1,8 +1,30 @@
 #![no_std]
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+use backoff::Backoff;
+use coding::{Decoder, Pulse};
+use config::{Config, Encoding, Role, Sampling, Timing};
 use core::mem::size_of;
-use embedded_hal::blocking::delay::DelayMs;
 use embedded_hal::digital::v2::{InputPin, OutputPin};
+use handshake::Capabilities;
+use idle::IdleWait;
+use observer::{Direction, WireObserver};
+use packet::Link;
+use poll::Poller;
+use scrambler::Scrambler;
+use snapshot::Activity;
+use stats::Stats;
+use token::Token;
+use units::{PhaseDelay, Unit};
 const BUF_SIZE: usize = 8;
+pub const BREAK_PHASES: u16 = 32;
+/// Version of the on-wire format, major in the high byte. Bumped whenever a
+/// change would make old and new firmware misunderstand each other.
+pub const WIRE_FORMAT_VERSION: u16 = 0x0100;
+
+/// Whether two wire format versions can talk: same major version.
+pub const fn compatible(a: u16, b: u16) -> bool {
+    return a >> 8 == b >> 8;
+}
 
 macro_rules! io_err {
     ( $i : expr ) => {
@@ -10,12 +32,88 @@ macro_rules! io_err {
     };
 }
 
-#[derive(Debug)]
+pub mod adapters;
+pub mod addr;
+pub mod ascii;
+pub mod backoff;
+pub mod buffer;
+pub mod builder;
+pub mod calibrate;
+pub mod capture;
+pub mod coding;
+pub mod config;
+pub mod control;
+pub mod crc;
+pub mod descriptor;
+pub mod discovery;
+pub mod endian;
+pub mod event;
+pub mod frame;
+pub mod handshake;
+pub mod idle;
+pub mod large;
+pub mod led;
+pub mod lin;
+pub mod manchester;
+#[cfg(feature = "serde")]
+pub mod message;
+pub mod observer;
+pub mod owned;
+pub mod packet;
+pub mod poll;
+pub mod queue;
+pub mod recover;
+pub mod registers;
+mod ring;
+pub mod scrambler;
+#[cfg(feature = "secure")]
+pub mod secure;
+#[cfg(feature = "shared")]
+pub mod shared;
+pub mod snapshot;
+pub mod stats;
+pub mod suspend;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod token;
+pub mod uart;
+pub mod units;
+pub mod window;
+pub mod word;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Error {
     Busy,
     Unavailable,
     IO,
     NoResponse,
+    Framing,
+    BreakReceived,
+    Marginal,
+    Crc,
+    Codec,
+    Demoted,
+    Timeout,
+    Calibration,
+    IncompatiblePeer,
+    Parity,
+    /// A frame with the wrong number of bits: the peers disagree on where
+    /// frames start. `recover` brings both back to idle.
+    Desync,
+    FrameTooLarge,
+    /// The line stopped changing in the middle of a frame, see
+    /// `Config::bit_timeout`.
+    Truncated {
+        bits_received: u8,
+    },
+    /// A secured frame failed its tag check: corrupted or forged.
+    Unauthenticated,
+    /// A secured frame with a counter that was already seen.
+    Replay,
+    /// A pulse outside all of `Config::pulse_bands`.
+    InvalidPulse,
+    /// An argument the transfer cannot work with, e.g. a zero timer rate.
+    Config,
 }
 
 impl Error {
@@ -25,16 +123,100 @@ impl Error {
             Self::Busy => "busy",
             Self::NoResponse => "no response",
             Self::Unavailable => "unavailable",
+            Self::Framing => "framing",
+            Self::BreakReceived => "break received",
+            Self::Marginal => "marginal",
+            Self::Crc => "crc mismatch",
+            Self::Codec => "codec",
+            Self::Demoted => "demoted to listener",
+            Self::Timeout => "timeout",
+            Self::Calibration => "calibration",
+            Self::IncompatiblePeer => "incompatible peer",
+            Self::Parity => "parity",
+            Self::Desync => "desync",
+            Self::FrameTooLarge => "frame too large",
+            Self::Truncated { .. } => "truncated",
+            Self::Unauthenticated => "unauthenticated",
+            Self::Replay => "replay",
+            Self::InvalidPulse => "invalid pulse",
+            Self::Config => "config",
+        }
+    }
+
+    /// Stable non-zero number for the error, e.g. for crash records.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Busy => 1,
+            Self::Unavailable => 2,
+            Self::IO => 3,
+            Self::NoResponse => 4,
+            Self::Framing => 5,
+            Self::BreakReceived => 6,
+            Self::Marginal => 7,
+            Self::Crc => 8,
+            Self::Codec => 9,
+            Self::Demoted => 10,
+            Self::Timeout => 11,
+            Self::Calibration => 12,
+            Self::IncompatiblePeer => 13,
+            Self::Parity => 14,
+            Self::Desync => 15,
+            Self::FrameTooLarge => 16,
+            Self::Truncated { .. } => 17,
+            Self::Unauthenticated => 18,
+            Self::Replay => 19,
+            Self::InvalidPulse => 20,
+            Self::Config => 21,
         }
     }
 }
 
-// pub trait ReadWrite {
-// fn write(&mut self, data: u8, delay: &mut impl DelayMs<u8>) -> Result<(), Error>;
-// fn read(&mut self, delay: &mut impl DelayMs<u8>) -> Result<u8, Error>;
-// }
+/// A byte transport. The frame, checksum, message and packet layers run
+/// over any of them; the hooks below have defaults for transports that keep
+/// no settings or statistics of their own.
+pub trait ReadWrite<T> {
+    fn write(&mut self, data: u8, delay: &mut impl PhaseDelay<T>) -> Result<(), Error>;
+    fn read(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error>;
+
+    /// Like `read`, but fails with `Error::Timeout` if no byte starts within
+    /// `timeout` phases. Transports without a phase clock wait as `read`
+    /// does.
+    fn read_timeout(&mut self, _timeout: u16, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return self.read(delay);
+    }
+
+    /// The settings the layers above follow.
+    fn config(&self) -> Config {
+        return Config::default();
+    }
+
+    /// Books `result` the way the transport books its own failures; the
+    /// layers above pass their checksum and framing errors through it.
+    fn settle<R>(&mut self, result: Result<R, Error>) -> Result<R, Error> {
+        return result;
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8], delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        for byte in buf.iter_mut() {
+            *byte = self.read(delay)?;
+        }
+        return Ok(());
+    }
+
+    /// Sends `buf`, then overwrites it with a reply of the same length.
+    fn transfer_in_place(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        for &byte in buf.iter() {
+            self.write(byte, delay)?;
+        }
+        return self.read_exact(buf, delay);
+    }
+}
 
-pub struct HalfDuplexWire<F2, F1, I, O, T>
+pub struct HalfDuplexWire<F2, F1, I, O, T, V = ()>
 where
     F1: Fn(O) -> I,
     F2: Fn(I) -> O,
@@ -45,21 +227,156 @@ where
     into_input: F1,
     into_output: F2,
     delay: T,
+    config: Config,
+    activity: Activity,
+    last_error: Option<Error>,
+    inflight: u8,
+    bits: u8,
+    stats: Stats,
+    role: Role,
+    busy_streak: u8,
+    tx_scrambler: Scrambler,
+    rx_scrambler: Scrambler,
+    observer: V,
+    link: Link,
+    timing: Timing,
+    low_power: bool,
+    poller: Poller<O>,
+    token: Token,
+    token_requested: bool,
+    backoff: Backoff,
+    pull_up: Option<fn(&mut I, bool)>,
+    peer_caps: Option<Capabilities>,
 }
 
-// impl<F2, F1, I, O, T> ReadWrite for HalfDuplexWire<F2, F1, I, O, T>
-impl<F2, F1, I, O, T> HalfDuplexWire<F2, F1, I, O, T>
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
 where
     F1: Fn(O) -> I,
     F2: Fn(I) -> O,
     I: InputPin,
     O: OutputPin,
     T: Copy,
+    V: WireObserver,
 {
     fn bring_back_pin(&mut self, pin: I) {
         self.pin = Some(pin);
     }
-    pub fn write(&mut self, data: u8, delay: &mut impl DelayMs<T>) -> Result<(), Error> {
+
+    /// Switches `pin` to output, with the pull-up off.
+    pub(crate) fn drive_pin(&mut self, mut pin: I) -> O {
+        if let Some(pull_up) = self.pull_up {
+            pull_up(&mut pin, false);
+        }
+        return (self.into_output)(pin);
+    }
+
+    /// Switches `pin` back to input, with the pull-up on.
+    pub(crate) fn release_pin(&mut self, pin: O) -> I {
+        let mut pin = (self.into_input)(pin);
+        if let Some(pull_up) = self.pull_up {
+            pull_up(&mut pin, true);
+        }
+        return pin;
+    }
+
+    pub(crate) fn settle<R>(&mut self, result: Result<R, Error>) -> Result<R, Error> {
+        self.activity = Activity::Idle;
+        if let Err(e) = result {
+            self.last_error = Some(e);
+            self.stats.record(e);
+            self.observer.on_error(e);
+        }
+        return result;
+    }
+
+    pub fn write(&mut self, data: u8, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return self.write_unit(&[data], delay);
+    }
+
+    /// Sends up to 4 bytes as one frame, first byte first, backing off and
+    /// retrying a busy bus as `Config::csma` asks.
+    pub(crate) fn write_unit(
+        &mut self,
+        bytes: &[u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let attempts = self.config.csma.unwrap_or(0);
+        let mut attempt = 0;
+        loop {
+            match self.write_unit_once(bytes, delay) {
+                Err(Error::Busy) if attempt < attempts => {
+                    self.back_off(attempt, delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn write_unit_once(
+        &mut self,
+        bytes: &[u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        if !self.may_transmit() {
+            return Err(Error::Unavailable);
+        }
+
+        self.activity = Activity::Transmitting;
+        self.inflight = bytes.last().copied().unwrap_or(0);
+        self.bits = 0;
+        let mut scrambler = self.tx_scrambler;
+        let mut word = 0u32;
+        for &data in bytes {
+            let data = self.config.bit_order.apply(data);
+            let symbol = if self.config.scramble {
+                scrambler.scramble(data)
+            } else {
+                data
+            };
+            word = word << 8 | symbol as u32;
+        }
+
+        let mut result = self.write_word(word, 8 * bytes.len() as u8, delay);
+        match result {
+            Ok(()) => {
+                self.tx_scrambler = scrambler;
+                self.stats.frames_sent = self.stats.frames_sent.wrapping_add(1);
+                self.observer.on_frame(Direction::Tx, bytes);
+                self.busy_streak = 0;
+            }
+            Err(Error::Busy) => {
+                self.busy_streak = self.busy_streak.saturating_add(1);
+                if let Some(n) = self.config.fallback_after {
+                    if self.busy_streak >= n {
+                        self.stats.record(Error::Busy);
+                        self.role = Role::Listener;
+                        self.busy_streak = 0;
+                        result = Err(Error::Demoted);
+                    }
+                }
+            }
+            Err(_) => {}
+        }
+        return self.settle(result);
+    }
+
+    pub(crate) fn write_byte(
+        &mut self,
+        data: u8,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return self.write_word(data as u32, 8, delay);
+    }
+
+    /// Sends the low `bits` bits of `word`, most significant first, in a
+    /// single frame.
+    fn write_word(
+        &mut self,
+        word: u32,
+        bits: u8,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
         let pin = match self.pin.take() {
             Some(s) => s,
             None => return Err(Error::Unavailable),
@@ -70,70 +387,325 @@ where
             return Err(Error::Busy);
         }
 
-        self.skip_phase(delay, 4);
+        self.skip_phase(delay, self.config.frame_gap);
 
         if io_err!(pin.is_low())? {
             self.bring_back_pin(pin);
             return Err(Error::Busy);
         }
 
-        let mut pin = (self.into_output)(pin);
-
-        pin.set_low().ok();
-
-        self.skip_phase(delay, 4);
-
-        let mut mask = 0x80;
-        for _ in 0..8 {
-            if data & mask != 0 {
+        let mut pin = self.drive_pin(pin);
+        let mut started = 0u8;
+        for segment in coding::encode_word(&self.config, word, bits) {
+            if let Some(bit) = segment.bit {
+                // The bit before this one is complete.
+                self.bits = started.min(bits);
+                started = started.saturating_add(1);
+                self.observer.on_bit(Direction::Tx, bit);
+            }
+            if segment.high {
                 pin.set_high().ok();
-                self.skip_phase(delay, 4);
-                pin.set_low().ok();
-                self.skip_phase(delay, 4);
             } else {
-                pin.set_high().ok();
-                self.skip_phase(delay, 2);
                 pin.set_low().ok();
-                self.skip_phase(delay, 6);
             }
-
-            mask >>= 1;
+            self.skip_phase(delay, segment.phases);
         }
+        self.bits = bits;
 
-        let pin = (self.into_input)(pin);
+        let pin = self.release_pin(pin);
         self.bring_back_pin(pin);
         return Ok(());
     }
 
-    pub fn read(&mut self, delay: &mut impl DelayMs<T>) -> Result<u8, Error> {
+    pub fn read(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return self.read_with_idle(&mut (), delay);
+    }
+
+    /// Like `read`, but calls `idle` instead of spinning while waiting for
+    /// an edge.
+    pub fn read_with_idle(
+        &mut self,
+        idle: &mut impl IdleWait,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<u8, Error> {
+        let mut buf = [0u8; 1];
+        self.read_unit(&mut buf, idle, delay)?;
+        return Ok(buf[0]);
+    }
+
+    /// Receives one frame of `buf.len()` bytes, at most 4, into `buf`.
+    pub(crate) fn read_unit(
+        &mut self,
+        buf: &mut [u8],
+        idle: &mut impl IdleWait,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        self.activity = Activity::Receiving;
+        self.inflight = 0;
+        self.bits = 0;
+        let result = self
+            .read_word(8 * buf.len() as u8, idle, delay)
+            .map(|word| self.deliver_rx(word, buf));
+        return self.settle(result);
+    }
+
+    /// Turns the raw word of a received frame into its `buf.len()` bytes,
+    /// descrambled and in bit order, and counts and reports the frame. Every
+    /// receive path ends here.
+    pub(crate) fn deliver_rx(&mut self, word: u32, buf: &mut [u8]) {
+        self.stats.frames_received = self.stats.frames_received.wrapping_add(1);
+        let last = buf.len() - 1;
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let symbol = (word >> (8 * (last - i))) as u8;
+            let data = if self.config.scramble {
+                self.rx_scrambler.descramble(symbol)
+            } else {
+                symbol
+            };
+            *byte = self.config.bit_order.apply(data);
+        }
+        self.observer.on_frame(Direction::Rx, buf);
+    }
+
+    /// Like `read`, but gives up with `Error::Timeout` if no frame starts
+    /// within `timeout` phases.
+    pub fn read_timeout(
+        &mut self,
+        timeout: u16,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<u8, Error> {
+        if !self.frame_starts_within(timeout, delay)? {
+            return self.settle(Err(Error::Timeout));
+        }
+
+        return self.read(delay);
+    }
+
+    /// Polls once per phase whether the line went low within `timeout` phases.
+    fn frame_starts_within(
+        &self,
+        timeout: u16,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<bool, Error> {
+        let pin = match &self.pin {
+            Some(s) => s,
+            None => return Err(Error::Unavailable),
+        };
+
+        let mut waited = 0u16;
+        while io_err!(pin.is_high())? {
+            if waited >= timeout {
+                return Ok(false);
+            }
+            self.phase(delay);
+            waited += 1;
+        }
+
+        return Ok(true);
+    }
+
+    /// Receives a frame that has to carry exactly `expected` bits, at most 32.
+    fn read_word(
+        &mut self,
+        expected: u8,
+        idle: &mut impl IdleWait,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<u32, Error> {
         let pin = match self.pin.take() {
             Some(s) => s,
             None => return Err(Error::Unavailable),
         };
 
-        let mut ed = EdgeDetector::new(pin);
+        if let Err(e) = self.wait_start(&pin, idle, delay) {
+            self.bring_back_pin(pin);
+            return Err(e);
+        }
+
+        let mut decoder = Decoder::with_bits(&self.config, expected);
+        decoder.begin();
 
-        let mut data = 0u8;
+        if self.config.encoding == Encoding::Manchester {
+            let result = self.read_manchester(&pin, &mut decoder, delay);
+            self.bring_back_pin(pin);
+            return result;
+        }
+
+        let mut ed = EdgeDetector::with_filter(pin, self.config.filter_depth);
+        let mut waited = 0u32;
 
         loop {
             if ed.risig_edge() {
-                self.skip_phase(delay, 3);
+                waited = 0;
+                let pulse = match self.config.sampling {
+                    Sampling::Measured => self.measure_pulse(&mut ed, delay),
+                    _ => self.sample_pulse(&mut ed, delay),
+                };
+                let result = pulse.and_then(|pulse| {
+                    if let Pulse::Bit(bit) = pulse {
+                        self.observer.on_bit(Direction::Rx, bit);
+                    }
+                    return decoder.push(pulse);
+                });
+                self.inflight = decoder.word() as u8;
+                self.bits = decoder.bits();
 
-                let tmp = io_err!(ed.is_high())?;
+                match result {
+                    Ok(None) => {}
+                    Ok(Some(word)) => {
+                        self.pin = Some(ed.release());
+                        return Ok(word);
+                    }
+                    Err(e) => {
+                        self.pin = Some(ed.release());
+                        return Err(e);
+                    }
+                }
+            } else if let Some(limit) = self.bit_timeout_ticks() {
+                if waited >= limit {
+                    self.pin = Some(ed.release());
+                    return Err(Error::Truncated {
+                        bits_received: decoder.bits(),
+                    });
+                }
+                delay.delay_phase(self.delay);
+                waited += 1;
+            } else if ed.pending == 0 {
+                // Only sleep on a quiet line: a filter confirming a change
+                // needs further samples without another edge to wake us.
+                idle.wait();
+            }
+        }
+    }
 
-                self.skip_phase(delay, 3);
+    /// `Config::bit_timeout` in delay ticks.
+    fn bit_timeout_ticks(&self) -> Option<u32> {
+        let limit = self.config.bit_timeout? as u32;
+        return Some(limit * self.timing.ticks_per_phase as u32);
+    }
+
+    /// Waits until the line leaves level `high`, with no bound unless
+    /// `Config::bit_timeout` is set.
+    pub(crate) fn wait_change(
+        &self,
+        pin: &I,
+        high: bool,
+        bits_received: u8,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let limit = self.bit_timeout_ticks();
+        let mut waited = 0u32;
 
-                if io_err!(ed.is_high())? {
-                    break;
-                } else {
-                    data <<= 1;
-                    data |= tmp as u8;
+        while io_err!(pin.is_high())? == high {
+            if let Some(limit) = limit {
+                if waited >= limit {
+                    return Err(Error::Truncated {
+                        bits_received: bits_received,
+                    });
                 }
+                delay.delay_phase(self.delay);
+                waited += 1;
+            }
+        }
+        return Ok(());
+    }
+
+    /// Waits for a low period that lasts at least half the start condition,
+    /// so short glitches on an idle line are not taken for a frame.
+    fn wait_start(
+        &mut self,
+        pin: &I,
+        idle: &mut impl IdleWait,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        loop {
+            while io_err!(pin.is_high())? {
+                idle.wait();
+            }
+
+            self.skip_phase(delay, self.config.start_phases / 2);
+
+            if io_err!(pin.is_low())? {
+                return Ok(());
             }
         }
+    }
 
-        self.pin = Some(ed.release());
-        return Ok(data);
+    /// Decides the bit of a pulse at fixed sample points; a line that stays
+    /// high is the stop.
+    fn sample_pulse(
+        &mut self,
+        ed: &mut EdgeDetector<I>,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Pulse, Error> {
+        let bit = self.sample_bit(ed, delay)?;
+        self.skip_phase(delay, 3);
+        if io_err!(ed.is_high())? {
+            return Ok(Pulse::Stop);
+        }
+        return Ok(Pulse::Bit(bit));
+    }
+
+    /// Times the pulse in delay ticks and classifies it by
+    /// `Config::pulse_bands`.
+    fn measure_pulse(
+        &mut self,
+        ed: &mut EdgeDetector<I>,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Pulse, Error> {
+        let bands = self.config.pulse_bands;
+        let per_phase = self.timing.ticks_per_phase as u32;
+        let mut ticks = 0u32;
+
+        while io_err!(ed.is_high())? {
+            if ticks * 8 > bands.stop as u32 * per_phase {
+                return Ok(Pulse::Stop);
+            }
+            delay.delay_phase(self.delay);
+            ticks += 1;
+        }
+
+        return coding::classify(&bands, ticks * 8 / per_phase);
+    }
+
+    fn sample_bit(
+        &mut self,
+        ed: &mut EdgeDetector<I>,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<bool, Error> {
+        match self.config.sampling {
+            // Measured pulses never come here.
+            Sampling::Single | Sampling::Measured => {
+                self.skip_phase(delay, 3);
+                return io_err!(ed.is_high());
+            }
+            Sampling::Double => {
+                let per_phase = self.timing.ticks_per_phase as u32;
+                let half = per_phase / 2;
+                self.ticks(delay, 3 * per_phase - half);
+                let early = io_err!(ed.is_high())?;
+                self.ticks(delay, 2 * half);
+                let late = io_err!(ed.is_high())?;
+
+                if early != late {
+                    return Err(Error::Marginal);
+                }
+                return Ok(late);
+            }
+            Sampling::Majority(n) => {
+                // One read per tick, centred on phase 3.
+                let per_phase = self.timing.ticks_per_phase as u32;
+                let spread = (n as u32).saturating_sub(1);
+                self.ticks(delay, (3 * per_phase).saturating_sub(spread / 2));
+                let mut high = 0u16;
+                for i in 0..n {
+                    if i > 0 {
+                        self.ticks(delay, 1);
+                    }
+                    high += io_err!(ed.is_high())? as u16;
+                }
+                return Ok(high * 2 > n as u16);
+            }
+        }
     }
 }
 
@@ -145,35 +717,306 @@ where
     O: OutputPin,
     T: Copy,
 {
-    pub fn new(pin: I, into_output: F2, into_input: F1, delay: T) -> Self {
+    /// `delay` is the duration of one phase, tagged as `Millis` or `Micros`.
+    pub fn new(pin: I, into_output: F2, into_input: F1, delay: T) -> Self
+    where
+        T: Unit,
+    {
         HalfDuplexWire {
             pin: Some(pin),
             into_input: into_input,
             into_output: into_output,
             delay: delay,
+            config: Config::default(),
+            activity: Activity::Idle,
+            last_error: None,
+            inflight: 0,
+            bits: 0,
+            stats: Stats::default(),
+            role: Role::Master,
+            busy_streak: 0,
+            tx_scrambler: Scrambler::new(),
+            rx_scrambler: Scrambler::new(),
+            observer: (),
+            link: Link::default(),
+            timing: Timing::default(),
+            low_power: false,
+            poller: Poller::new(),
+            token: Token::Passed,
+            token_requested: false,
+            backoff: Backoff::new(),
+            pull_up: None,
+            peer_caps: None,
+        }
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.set_config(config);
+        return self;
+    }
+
+    /// Lets the wire manage the internal pull-up of the pin: `hook` is called
+    /// with `true` whenever the pin becomes an input and with `false` before
+    /// it drives the line. The pull-up is switched on right away.
+    pub fn with_pull_up(mut self, hook: fn(&mut I, bool)) -> Self {
+        if let Some(pin) = self.pin.as_mut() {
+            hook(pin, true);
         }
+        self.pull_up = Some(hook);
+        return self;
+    }
+
+    pub fn config(&self) -> &Config {
+        return &self.config;
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+        self.timing = if self.low_power {
+            config.low_power_timing
+        } else {
+            config.timing
+        };
+    }
+
+    /// The timing profile currently in use.
+    pub fn timing(&self) -> Timing {
+        return self.timing;
+    }
+
+    /// Runs `f` with `timing` as the active profile and restores the previous
+    /// one afterwards, e.g. to talk to a slower peer on the same pin.
+    pub fn with_timing<R>(&mut self, timing: Timing, f: impl FnOnce(&mut Self) -> R) -> R {
+        let previous = self.timing;
+        self.timing = timing;
+        let result = f(self);
+        self.timing = previous;
+        return result;
+    }
+
+    pub fn write_with(
+        &mut self,
+        timing: Timing,
+        data: u8,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return self.with_timing(timing, |wire| wire.write(data, delay));
+    }
+
+    pub fn read_with(
+        &mut self,
+        timing: Timing,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<u8, Error> {
+        return self.with_timing(timing, |wire| wire.read(delay));
+    }
+
+    pub fn role(&self) -> Role {
+        return self.role;
     }
 
-    pub fn skip_phase(&mut self, delay: &mut impl DelayMs<T>, n: u8) {
+    pub fn set_role(&mut self, role: Role) {
+        self.role = role;
+        self.busy_streak = 0;
+    }
+
+    /// Waits one phase of the active timing profile.
+    pub(crate) fn phase(&self, delay: &mut impl PhaseDelay<T>) {
+        for _ in 0..self.timing.ticks_per_phase {
+            delay.delay_phase(self.delay);
+        }
+    }
+
+    /// `n` delay ticks, fractions of a phase under `Timing`.
+    pub(crate) fn ticks(&self, delay: &mut impl PhaseDelay<T>, n: u32) {
+        for _ in 0..n {
+            delay.delay_phase(self.delay);
+        }
+    }
+
+    pub fn skip_phase(&mut self, delay: &mut impl PhaseDelay<T>, n: u8) {
         for _ in 0..n {
-            delay.delay_ms(self.delay);
+            self.phase(delay);
         }
     }
 
-    pub fn stream_request(&mut self, delay: &mut impl DelayMs<T>) -> Result<(), Error> {
+    pub fn stream_request(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
         if let Some(pin) = &self.pin {
             if io_err!(pin.is_high())? {
-                delay.delay_ms(self.delay);
+                self.phase(delay);
                 return Err(Error::NoResponse);
             } else {
                 return Ok(());
             }
         }
 
-        delay.delay_ms(self.delay);
+        self.phase(delay);
         return Err(Error::Unavailable);
     }
 
+    pub fn transfer_in_place(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return ReadWrite::transfer_in_place(self, buf, delay);
+    }
+
+    pub fn read_exact(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return ReadWrite::read_exact(self, buf, delay);
+    }
+
+    /// Sends `cmd`, then reads a reply of `resp.len()` bytes. The reply has
+    /// to start within `Config::response_timeout` phases of the line coming
+    /// free, or the query fails with `Error::NoResponse`; a reply that
+    /// stops early fails with `Error::Timeout`.
+    pub fn transfer(
+        &mut self,
+        cmd: &[u8],
+        resp: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        for &byte in cmd {
+            self.write(byte, delay)?;
+        }
+
+        let timeout = self.config.response_timeout;
+        self.wait_bus_free(1, timeout, delay)?;
+
+        for (i, byte) in resp.iter_mut().enumerate() {
+            *byte = match self.read_timeout(timeout, delay) {
+                Err(Error::Timeout) if i == 0 => return Err(Error::NoResponse),
+                result => result?,
+            };
+        }
+        return Ok(());
+    }
+
+    /// Waits for a byte, then keeps reading until the line stays idle for
+    /// `Config::response_timeout` phases or `buf` is full. Returns the
+    /// number of bytes received.
+    pub fn read_until_idle(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        buf[0] = self.read(delay)?;
+
+        let mut len = 1;
+        while len < buf.len() {
+            if !self.frame_starts_within(self.config.response_timeout, delay)? {
+                break;
+            }
+            buf[len] = self.read(delay)?;
+            len += 1;
+        }
+
+        return Ok(len);
+    }
+
+    /// Waits until the line has been continuously high for `min_idle_phases`
+    /// phases. Gives up with `Error::Busy` after `timeout` phases.
+    pub fn wait_bus_free(
+        &mut self,
+        min_idle_phases: u16,
+        timeout: u16,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let pin = match &self.pin {
+            Some(s) => s,
+            None => return Err(Error::Unavailable),
+        };
+
+        let mut idle = 0u16;
+        for _ in 0..timeout {
+            if io_err!(pin.is_high())? {
+                idle += 1;
+                if idle >= min_idle_phases {
+                    return Ok(());
+                }
+            } else {
+                idle = 0;
+            }
+
+            self.phase(delay);
+        }
+
+        self.stats.timeouts = self.stats.timeouts.wrapping_add(1);
+        return Err(Error::Busy);
+    }
+
+    /// Waits, up to `Config::response_timeout`, for a frame gap of idle line
+    /// before another attempt. A line that stays busy shows up in the
+    /// attempt itself, so running out of time here is not an error.
+    pub(crate) fn wait_retry_gap(&mut self, delay: &mut impl PhaseDelay<T>) {
+        let gap = self.config.frame_gap as u16;
+        let timeout = self.config.response_timeout;
+        self.wait_bus_free(gap, timeout, delay).ok();
+    }
+
+    /// Holds the line low for `phases` phases, e.g. `BREAK_PHASES`.
+    pub fn send_break(&mut self, phases: u16, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        let pin = match self.pin.take() {
+            Some(s) => s,
+            None => return Err(Error::Unavailable),
+        };
+
+        let mut pin = self.drive_pin(pin);
+
+        pin.set_low().ok();
+        for _ in 0..phases {
+            self.phase(delay);
+        }
+
+        let pin = self.release_pin(pin);
+        self.bring_back_pin(pin);
+        return Ok(());
+    }
+
+    /// Blocks until the line was low for at least `min_phases` phases and
+    /// has returned to idle.
+    pub fn wait_for_break(
+        &mut self,
+        min_phases: u16,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let pin = match &self.pin {
+            Some(s) => s,
+            None => return Err(Error::Unavailable),
+        };
+
+        let mut low = 0u16;
+        loop {
+            if io_err!(pin.is_low())? {
+                low = low.saturating_add(1);
+            } else if low >= min_phases {
+                return Ok(());
+            } else {
+                low = 0;
+            }
+
+            self.phase(delay);
+        }
+    }
+
     pub fn release(mut self) -> Result<I, Error> {
         let pin = match self.pin.take() {
             Some(s) => s,
@@ -183,15 +1026,15 @@ where
         return Ok(pin);
     }
 
-    pub fn get<U>(&mut self, delay: &mut impl DelayMs<T>) -> Result<U, Error>
+    pub fn get<U>(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<U, Error>
     where
         U: Copy,
     {
         let mut buf = [0u8; BUF_SIZE];
         let size = size_of::<U>();
 
-        for i in 0..size {
-            buf[i] = self.read(delay)?;
+        for byte in buf.iter_mut().take(size) {
+            *byte = self.read(delay)?;
         }
 
         let tmp = unsafe { &*(buf[0..size].as_ptr() as *const U) };
@@ -200,9 +1043,41 @@ where
     }
 }
 
+impl<F2, F1, I, O, T, V> ReadWrite<T> for HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    fn write(&mut self, data: u8, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return HalfDuplexWire::write(self, data, delay);
+    }
+
+    fn read(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return HalfDuplexWire::read(self, delay);
+    }
+
+    fn read_timeout(&mut self, timeout: u16, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return HalfDuplexWire::read_timeout(self, timeout, delay);
+    }
+
+    fn config(&self) -> Config {
+        return self.config;
+    }
+
+    fn settle<R>(&mut self, result: Result<R, Error>) -> Result<R, Error> {
+        return HalfDuplexWire::settle(self, result);
+    }
+}
+
 pub struct EdgeDetector<T> {
     pin: T,
     status: bool,
+    depth: u8,
+    pending: u8,
 }
 
 impl<T> EdgeDetector<T>
@@ -210,10 +1085,18 @@ where
     T: InputPin,
 {
     pub fn new(pin: T) -> Self {
+        return Self::with_filter(pin, 1);
+    }
+
+    /// Only reports a level change once `depth` consecutive samples agree on
+    /// it, so spikes shorter than that are ignored.
+    pub fn with_filter(pin: T, depth: u8) -> Self {
         let status = pin.is_high().unwrap_or(false);
         return Self {
             pin: pin,
             status: status,
+            depth: depth,
+            pending: 0,
         };
     }
 
@@ -221,11 +1104,18 @@ where
         let status = self.pin.is_high();
         if let Ok(status) = status {
             if status == self.status {
+                self.pending = 0;
                 return false;
-            } else {
-                self.status = status;
-                return status;
             }
+
+            self.pending += 1;
+            if self.pending < self.depth {
+                return false;
+            }
+
+            self.pending = 0;
+            self.status = status;
+            return status;
         } else {
             return false;
         }
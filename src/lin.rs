@@ -0,0 +1,142 @@
+//! LIN-style scheduling: the master owns the timing of the whole wire. For
+//! every slot of its schedule it sends a header, a break followed by a
+//! protected identifier, and the one slave that owns the identifier answers
+//! with a fixed-length response and checksum:
+//!
+//! ```text
+//! master: | break | pid |
+//! slave:                 | data (Slot::len bytes) | checksum |
+//! ```
+//!
+//! Slaves never speak unasked, so the latency of every message is fixed by
+//! the schedule.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire, BREAK_PHASES};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Identifiers are 6 bits wide, the top two bits of the pid are parity.
+pub const MAX_ID: u8 = 0x3f;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub id: u8,
+    /// Response length in bytes, without the checksum.
+    pub len: u8,
+    /// Idle phases after the response, padding the slot to its period.
+    pub gap: u16,
+}
+
+/// Cycles through a fixed table of slots.
+pub struct Schedule<'a> {
+    slots: &'a [Slot],
+    next: usize,
+}
+
+impl<'a> Schedule<'a> {
+    pub const fn new(slots: &'a [Slot]) -> Self {
+        Schedule {
+            slots: slots,
+            next: 0,
+        }
+    }
+
+    /// The slot to run next; wraps around at the end of the table.
+    pub fn advance(&mut self) -> Option<Slot> {
+        let slot = *self.slots.get(self.next)?;
+        self.next = (self.next + 1) % self.slots.len();
+        return Some(slot);
+    }
+}
+
+/// Adds the two LIN parity bits to a 6-bit identifier.
+pub fn protected_id(id: u8) -> u8 {
+    let id = id & MAX_ID;
+    let bit = |n: u8| (id >> n) & 1;
+    let p0 = bit(0) ^ bit(1) ^ bit(2) ^ bit(4);
+    let p1 = !(bit(1) ^ bit(3) ^ bit(4) ^ bit(5)) & 1;
+    return id | p0 << 6 | p1 << 7;
+}
+
+/// Inverted sum with end-around carry over the pid and the response.
+pub fn lin_checksum(pid: u8, data: &[u8]) -> u8 {
+    let mut sum = pid as u16;
+    for &byte in data {
+        sum += byte as u16;
+        if sum > 0xff {
+            sum -= 0xff;
+        }
+    }
+    return !(sum as u8);
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn send_header(&mut self, id: u8, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        self.send_break(BREAK_PHASES, delay)?;
+        return self.write(protected_id(id), delay);
+    }
+
+    /// Master side of one slot: sends the header and reads the response into
+    /// `buf`, which must hold `slot.len` bytes.
+    pub fn run_slot(
+        &mut self,
+        slot: Slot,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let len = slot.len as usize;
+        if buf.len() < len {
+            return Err(Error::Framing);
+        }
+
+        self.send_header(slot.id, delay)?;
+
+        let timeout = self.config.response_timeout;
+        for byte in buf[..len].iter_mut() {
+            *byte = self.read_timeout(timeout, delay)?;
+        }
+        let sum = self.read_timeout(timeout, delay)?;
+
+        for _ in 0..slot.gap {
+            self.phase(delay);
+        }
+
+        if sum != lin_checksum(protected_id(slot.id), &buf[..len]) {
+            return self.settle(Err(Error::Crc));
+        }
+        return Ok(());
+    }
+
+    /// Slave side: waits for the next header and returns its identifier.
+    /// Headers with bad parity fail with `Error::Framing`.
+    pub fn wait_header(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        self.wait_for_break(BREAK_PHASES / 2, delay)?;
+
+        let pid = self.read_timeout(self.config.response_timeout, delay)?;
+        if protected_id(pid) != pid {
+            return self.settle(Err(Error::Framing));
+        }
+        return Ok(pid & MAX_ID);
+    }
+
+    /// Answers the header for `id` with `data`.
+    pub fn respond(
+        &mut self,
+        id: u8,
+        data: &[u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        for &byte in data {
+            self.write(byte, delay)?;
+        }
+        return self.write(lin_checksum(protected_id(id), data), delay);
+    }
+}
@@ -0,0 +1,78 @@
+//! Manchester line coding. Every bit has a transition in its middle, low to
+//! high for a one and high to low for a zero:
+//!
+//! ```text
+//!        start      sync  1    0
+//! ‾‾‾‾|__________|‾‾|__|__|‾‾|‾‾|__|‾‾‾
+//! ```
+//!
+//! The sync bit, always a zero, ends the start condition with a rising edge.
+//! The receiver waits for every mid-bit transition before it samples the
+//! second half, so it picks up the sender's clock again on every bit and
+//! tolerates far more drift than pulse-width coding.
+use crate::coding::{Decoder, Pulse};
+use crate::observer::{Direction, WireObserver};
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Phases per half bit.
+pub const MANCHESTER_HALF: u8 = 2;
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Samples the sync bit and the data and parity bits into `decoder`
+    /// once the start condition was seen, then waits for the idle line.
+    /// Every half is read a quarter bit after the edge before it.
+    pub(crate) fn read_manchester(
+        &mut self,
+        pin: &I,
+        decoder: &mut Decoder,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<u32, Error> {
+        let quarter = MANCHESTER_HALF / 2;
+
+        self.wait_change(pin, false, 0, delay)?;
+        self.skip_phase(delay, quarter);
+
+        loop {
+            let first = io_err!(pin.is_high())?;
+            decoder.push_half(first)?;
+            self.wait_change(pin, first, decoder.bits(), delay)?;
+            self.skip_phase(delay, quarter);
+
+            if let Some(bit) = decoder.push_half(io_err!(pin.is_high())?)? {
+                self.observer.on_bit(Direction::Rx, bit);
+                self.inflight = decoder.word() as u8;
+                self.bits = decoder.bits();
+            }
+            self.skip_phase(delay, MANCHESTER_HALF);
+            if decoder.is_complete() {
+                break;
+            }
+        }
+
+        // The frame is over once the line stays high for three halves,
+        // longer than any level inside a frame. Any transition before that
+        // is a bit too many, which makes the frame fail as overlong.
+        let mut high = 0;
+        while high < 3 * MANCHESTER_HALF {
+            if io_err!(pin.is_high())? {
+                high += 1;
+                self.phase(delay);
+            } else {
+                high = 0;
+                decoder.push(Pulse::Bit(false))?;
+                self.wait_change(pin, false, decoder.bits(), delay)?;
+            }
+        }
+        return decoder.finish();
+    }
+}
@@ -0,0 +1,80 @@
+//! Structured messages: postcard-serialized values framed with COBS, so a
+//! zero byte always marks the end of a message.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire, ReadWrite};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Messages over any byte transport.
+pub trait MessageTransport<T>: ReadWrite<T> {
+    /// Serializes `msg` into `buf` and sends it. `buf` has to hold the
+    /// encoded message plus COBS overhead.
+    fn send_msg<M: Serialize>(
+        &mut self,
+        msg: &M,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        let frame = postcard::to_slice_cobs(msg, buf).map_err(|_| Error::Codec)?;
+
+        for &byte in frame.iter() {
+            self.write(byte, delay)?;
+        }
+        return Ok(());
+    }
+
+    /// Receives one message, using `buf` as scratch space for the frame.
+    fn recv_msg<M: DeserializeOwned>(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<M, Error> {
+        let mut len = 0;
+        loop {
+            if len == buf.len() {
+                return Err(Error::Framing);
+            }
+
+            let byte = self.read(delay)?;
+            buf[len] = byte;
+            len += 1;
+
+            if byte == 0 {
+                break;
+            }
+        }
+
+        return postcard::from_bytes_cobs(&mut buf[..len]).map_err(|_| Error::Codec);
+    }
+}
+
+impl<T, W: ReadWrite<T>> MessageTransport<T> for W {}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn send_msg<M: Serialize>(
+        &mut self,
+        msg: &M,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return MessageTransport::send_msg(self, msg, buf, delay);
+    }
+
+    pub fn recv_msg<M: DeserializeOwned>(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<M, Error> {
+        return MessageTransport::recv_msg(self, buf, delay);
+    }
+}
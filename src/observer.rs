@@ -0,0 +1,73 @@
+//! Hook for watching the wire at work, e.g. to drive activity LEDs, to
+//! log traffic or to mark bits for a logic analyzer. All methods default
+//! to doing nothing; `()` is the observer of a wire that has none.
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Tx,
+    Rx,
+}
+
+pub trait WireObserver {
+    /// Called as every bit starts going out, or once a received bit was
+    /// decided, parity included. This runs between the phases of a frame,
+    /// so it has to be quick: log to a buffer or toggle a debug pin.
+    fn on_bit(&mut self, _dir: Direction, _value: bool) {}
+
+    /// Called after a frame went out or came in completely.
+    fn on_frame(&mut self, _dir: Direction, _bytes: &[u8]) {}
+
+    fn on_error(&mut self, _e: Error) {}
+}
+
+impl WireObserver for () {}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Replaces the observer, keeping everything else.
+    pub fn with_observer<W: WireObserver>(self, observer: W) -> HalfDuplexWire<F2, F1, I, O, T, W> {
+        HalfDuplexWire {
+            pin: self.pin,
+            into_input: self.into_input,
+            into_output: self.into_output,
+            delay: self.delay,
+            config: self.config,
+            activity: self.activity,
+            last_error: self.last_error,
+            inflight: self.inflight,
+            bits: self.bits,
+            stats: self.stats,
+            role: self.role,
+            busy_streak: self.busy_streak,
+            tx_scrambler: self.tx_scrambler,
+            rx_scrambler: self.rx_scrambler,
+            observer: observer,
+            link: self.link,
+            timing: self.timing,
+            low_power: self.low_power,
+            poller: self.poller,
+            token: self.token,
+            token_requested: self.token_requested,
+            backoff: self.backoff,
+            pull_up: self.pull_up,
+            peer_caps: self.peer_caps,
+        }
+    }
+
+    pub fn observer(&self) -> &V {
+        return &self.observer;
+    }
+
+    pub fn observer_mut(&mut self) -> &mut V {
+        return &mut self.observer;
+    }
+}
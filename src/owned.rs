@@ -0,0 +1,105 @@
+//! A wire bundled with its own delay provider, for wires that live in a
+//! struct or a shared resource where no delay is at hand at the call site.
+//! The common transfers come without a delay argument; everything else is
+//! reachable through `with`, which lends out both halves.
+//!
+//! Wires that share one timer with other drivers keep using the borrowed
+//! delay API of `HalfDuplexWire` directly.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub struct OwnedDelay<W, D> {
+    wire: W,
+    delay: D,
+}
+
+impl<W, D> OwnedDelay<W, D> {
+    pub fn wire(&self) -> &W {
+        return &self.wire;
+    }
+
+    pub fn wire_mut(&mut self) -> &mut W {
+        return &mut self.wire;
+    }
+
+    pub fn delay_mut(&mut self) -> &mut D {
+        return &mut self.delay;
+    }
+
+    /// Runs `f` with the wire and the delay, for the calls that have no
+    /// delay-free counterpart here.
+    pub fn with<R>(&mut self, f: impl FnOnce(&mut W, &mut D) -> R) -> R {
+        return f(&mut self.wire, &mut self.delay);
+    }
+
+    pub fn into_parts(self) -> (W, D) {
+        return (self.wire, self.delay);
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Hands `delay` to the wire for good.
+    pub fn with_owned_delay<D: PhaseDelay<T>>(self, delay: D) -> OwnedDelay<Self, D> {
+        return OwnedDelay {
+            wire: self,
+            delay: delay,
+        };
+    }
+}
+
+impl<F2, F1, I, O, T, V, D> OwnedDelay<HalfDuplexWire<F2, F1, I, O, T, V>, D>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+    D: PhaseDelay<T>,
+{
+    pub fn write(&mut self, data: u8) -> Result<(), Error> {
+        return self.wire.write(data, &mut self.delay);
+    }
+
+    pub fn read(&mut self) -> Result<u8, Error> {
+        return self.wire.read(&mut self.delay);
+    }
+
+    pub fn read_timeout(&mut self, timeout: u16) -> Result<u8, Error> {
+        return self.wire.read_timeout(timeout, &mut self.delay);
+    }
+
+    pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        return self.wire.read_exact(buf, &mut self.delay);
+    }
+
+    pub fn get<U: Copy>(&mut self) -> Result<U, Error> {
+        return self.wire.get(&mut self.delay);
+    }
+
+    pub fn stream_request(&mut self) -> Result<(), Error> {
+        return self.wire.stream_request(&mut self.delay);
+    }
+
+    pub fn transfer(&mut self, cmd: &[u8], resp: &mut [u8]) -> Result<(), Error> {
+        return self.wire.transfer(cmd, resp, &mut self.delay);
+    }
+
+    pub fn send_frame(&mut self, data: &[u8]) -> Result<(), Error> {
+        return self.wire.send_frame(data, &mut self.delay);
+    }
+
+    pub fn recv_frame(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        return self.wire.recv_frame(buf, &mut self.delay);
+    }
+}
@@ -0,0 +1,327 @@
+//! Stop-and-wait packets with sequence numbers.
+//!
+//! ```text
+//! | kind | seq | len | payload (len bytes) | crc (CrcEngine::WIDTH bytes) |
+//! ```
+//!
+//! The checksum covers everything before it. Every data packet is answered
+//! with an ack carrying the same sequence number; the sender retransmits
+//! until the ack arrives, and the receiver acks but drops repeated packets.
+//! A receiver with no room for a packet answers with a nak instead.
+use crate::crc::{crc_width, CrcEngine};
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire, ReadWrite};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const KIND_DATA: u8 = 0x01;
+pub const KIND_ACK: u8 = 0x02;
+/// Bus management command, broadcast and never acked; see `control`.
+pub const KIND_CONTROL: u8 = 0x03;
+/// Data for every device on the wire, never acked.
+pub const KIND_BROADCAST: u8 = 0x04;
+/// Refuses a data packet the receiver has no room for; the sender gives up
+/// with `Error::FrameTooLarge` instead of retrying.
+pub const KIND_NAK: u8 = 0x05;
+pub const HEADER_LEN: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Packet<'a> {
+    pub seq: u8,
+    pub payload: &'a [u8],
+}
+
+/// Sequence numbers of a packet link, kept by its transport.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Link {
+    pub(crate) tx_seq: u8,
+    pub(crate) rx_seq: Option<u8>,
+}
+
+fn packet_crc<C: CrcEngine>(crc: &mut C, header: &[u8], payload: &[u8]) -> u32 {
+    crc.reset();
+    crc.update(header);
+    crc.update(payload);
+    return crc.finish();
+}
+
+pub(crate) fn write_packet<T, W: ReadWrite<T>, C: CrcEngine>(
+    wire: &mut W,
+    header: [u8; HEADER_LEN],
+    payload: &[u8],
+    crc: &mut C,
+    delay: &mut impl PhaseDelay<T>,
+) -> Result<(), Error> {
+    let sum = packet_crc(crc, &header, payload);
+
+    for &byte in header.iter().chain(payload) {
+        wire.write(byte, delay)?;
+    }
+    for i in (0..crc_width::<C>(&wire.config())).rev() {
+        wire.write((sum >> (8 * i)) as u8, delay)?;
+    }
+    return Ok(());
+}
+
+/// Reads a packet into `buf` and returns its header. The first byte may
+/// take arbitrarily long, the rest has to follow within
+/// `Config::response_timeout`.
+fn read_packet<T, W: ReadWrite<T>, C: CrcEngine>(
+    wire: &mut W,
+    first: Option<u16>,
+    buf: &mut [u8],
+    crc: &mut C,
+    delay: &mut impl PhaseDelay<T>,
+) -> Result<[u8; HEADER_LEN], Error> {
+    let config = wire.config();
+    let timeout = config.response_timeout;
+    let mut header = [0u8; HEADER_LEN];
+
+    header[0] = match first {
+        Some(t) => wire.read_timeout(t, delay)?,
+        None => wire.read(delay)?,
+    };
+    for byte in header.iter_mut().skip(1) {
+        *byte = wire.read_timeout(timeout, delay)?;
+    }
+
+    let len = header[2] as usize;
+    let width = crc_width::<C>(&config);
+    if len > buf.len() || len > config.max_payload as usize {
+        // Read the rest of the packet, so the next header starts in sync.
+        for _ in 0..len + width {
+            wire.read_timeout(timeout, delay)?;
+        }
+        return wire.settle(Err(Error::Framing));
+    }
+    for byte in buf[..len].iter_mut() {
+        *byte = wire.read_timeout(timeout, delay)?;
+    }
+
+    let mut sum = 0u32;
+    for _ in 0..width {
+        sum = sum << 8 | wire.read_timeout(timeout, delay)? as u32;
+    }
+
+    if width > 0 && sum != packet_crc(crc, &header, &buf[..len]) {
+        return wire.settle(Err(Error::Crc));
+    }
+    return Ok(header);
+}
+
+/// Like `recv_packet`, but every packet has to start within `first`
+/// phases if given.
+pub(crate) fn recv_packet_within<'b, T, W: PacketTransport<T>, C: CrcEngine>(
+    wire: &mut W,
+    first: Option<u16>,
+    buf: &'b mut [u8],
+    crc: &mut C,
+    delay: &mut impl PhaseDelay<T>,
+) -> Result<Packet<'b>, Error> {
+    return recv_packet_accepting(wire, first, buf, crc, |_| true, delay);
+}
+
+/// Like `recv_packet_within`, but a data packet whose payload `accept`
+/// refuses is answered with a nak and fails with `Error::FrameTooLarge`.
+pub(crate) fn recv_packet_accepting<'b, T, W: PacketTransport<T>, C: CrcEngine>(
+    wire: &mut W,
+    first: Option<u16>,
+    buf: &'b mut [u8],
+    crc: &mut C,
+    mut accept: impl FnMut(&[u8]) -> bool,
+    delay: &mut impl PhaseDelay<T>,
+) -> Result<Packet<'b>, Error> {
+    loop {
+        let [kind, seq, len] = read_packet(wire, first, buf, crc, delay)?;
+        if kind == KIND_CONTROL && len > 0 {
+            wire.on_control(buf[0]);
+            continue;
+        }
+        if kind == KIND_BROADCAST {
+            return Ok(Packet {
+                seq: seq,
+                payload: &buf[..len as usize],
+            });
+        }
+        if kind != KIND_DATA {
+            continue;
+        }
+
+        if !accept(&buf[..len as usize]) {
+            wire.answer(|wire| write_packet(wire, [KIND_NAK, seq, 0], &[], crc, delay))?;
+            return wire.settle(Err(Error::FrameTooLarge));
+        }
+        wire.answer(|wire| write_packet(wire, [KIND_ACK, seq, 0], &[], crc, delay))?;
+
+        if wire.link().rx_seq == Some(seq) {
+            continue;
+        }
+        wire.link().rx_seq = Some(seq);
+
+        return Ok(Packet {
+            seq: seq,
+            payload: &buf[..len as usize],
+        });
+    }
+}
+
+/// Packets over a byte transport that keeps a `Link`. The hooks let a
+/// transport take part in bus management; by default control packets are
+/// dropped.
+pub trait PacketTransport<T>: ReadWrite<T> {
+    fn link(&mut self) -> &mut Link;
+
+    /// Called with the command of every control packet received.
+    fn on_control(&mut self, _command: u8) {}
+
+    /// Sends an ack through `f`, where the transport may need to lift its
+    /// own restrictions on sending.
+    fn answer<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R
+    where
+        Self: Sized,
+    {
+        return f(self);
+    }
+
+    /// Called before every retransmission.
+    fn on_retry(&mut self) {}
+
+    /// Sends `payload` (at most `Config::max_payload` bytes) and waits for
+    /// the ack, retrying up to `Config::retries` times before giving up with
+    /// `Error::NoResponse`. A nak fails at once with `Error::FrameTooLarge`.
+    fn send_packet<C: CrcEngine>(
+        &mut self,
+        payload: &[u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error>
+    where
+        Self: Sized,
+    {
+        let config = self.config();
+        if payload.len() > config.max_payload as usize {
+            return Err(Error::Framing);
+        }
+
+        let seq = self.link().tx_seq;
+        let header = [KIND_DATA, seq, payload.len() as u8];
+
+        for attempt in 0..=config.retries {
+            if attempt > 0 {
+                self.on_retry();
+            }
+
+            write_packet(self, header, payload, crc, delay)?;
+
+            let timeout = config.response_timeout;
+            match read_packet(self, Some(timeout), &mut [], crc, delay) {
+                Ok([KIND_ACK, s, _]) if s == seq => {
+                    self.link().tx_seq = seq.wrapping_add(1);
+                    return Ok(());
+                }
+                Ok([KIND_NAK, s, _]) if s == seq => return Err(Error::FrameTooLarge),
+                Ok(_) | Err(Error::Timeout | Error::Crc | Error::Framing) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        return Err(Error::NoResponse);
+    }
+
+    /// Sends `payload` to every device at once. There is no ack, so delivery
+    /// is best effort.
+    fn broadcast_packet<C: CrcEngine>(
+        &mut self,
+        payload: &[u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error>
+    where
+        Self: Sized,
+    {
+        if payload.len() > self.config().max_payload as usize {
+            return Err(Error::Framing);
+        }
+
+        let header = [KIND_BROADCAST, 0, payload.len() as u8];
+        return write_packet(self, header, payload, crc, delay);
+    }
+
+    /// Waits for the next new data or broadcast packet, acks it if needed and
+    /// returns a view of it in `buf`. Retransmissions of the last data packet
+    /// are acked and skipped.
+    fn recv_packet<'b, C: CrcEngine>(
+        &mut self,
+        buf: &'b mut [u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Packet<'b>, Error>
+    where
+        Self: Sized,
+    {
+        return recv_packet_within(self, None, buf, crc, delay);
+    }
+}
+
+impl<F2, F1, I, O, T, V> PacketTransport<T> for HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    fn link(&mut self) -> &mut Link {
+        return &mut self.link;
+    }
+
+    fn on_control(&mut self, command: u8) {
+        self.apply_control(command);
+    }
+
+    fn answer<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        return HalfDuplexWire::answer(self, f);
+    }
+
+    fn on_retry(&mut self) {
+        self.stats.retries = self.stats.retries.wrapping_add(1);
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn send_packet<C: CrcEngine>(
+        &mut self,
+        payload: &[u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return PacketTransport::send_packet(self, payload, crc, delay);
+    }
+
+    pub fn broadcast_packet<C: CrcEngine>(
+        &mut self,
+        payload: &[u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return PacketTransport::broadcast_packet(self, payload, crc, delay);
+    }
+
+    pub fn recv_packet<'b, C: CrcEngine>(
+        &mut self,
+        buf: &'b mut [u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<Packet<'b>, Error> {
+        return PacketTransport::recv_packet(self, buf, crc, delay);
+    }
+}
@@ -0,0 +1,345 @@
+//! Non-blocking transfers for cooperative schedulers. Instead of sleeping on
+//! a delay provider, the wire is stepped by `poll` once per main-loop pass;
+//! it keeps its own deadlines and moves the line by at most one phase per
+//! call. Both directions run in the same state machine, so a frame arriving
+//! while a write waits for its gap is still received.
+use crate::coding::{self, Decoder, Encoder, Pulse};
+use crate::config::Encoding;
+use crate::event::WireEvent;
+use crate::observer::{Direction, WireObserver};
+use crate::snapshot::Activity;
+use crate::units::Unit;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Free-running microsecond timestamp. Counts may wrap around.
+pub type Ticks = u32;
+
+pub(crate) enum PollState<O> {
+    Idle,
+    /// A write waiting for the line to stay idle for the frame gap.
+    Gap {
+        data: u8,
+        idle: u8,
+    },
+    Tx {
+        pin: O,
+        data: u8,
+        encoder: Encoder,
+        /// Bits begun so far.
+        begun: u8,
+        /// Polls left at the current level.
+        left: u8,
+    },
+    /// Counts polls at each level: a level seen by `n` polls lasted `n`
+    /// phases, give or take one.
+    Rx {
+        decoder: Decoder,
+        low: u8,
+        high: u8,
+    },
+}
+
+pub(crate) struct Poller<O> {
+    state: PollState<O>,
+    due: Option<Ticks>,
+    received: Option<u8>,
+}
+
+impl<O> Poller<O> {
+    pub(crate) const fn new() -> Self {
+        Poller {
+            state: PollState::Idle,
+            due: None,
+            received: None,
+        }
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Drops any polled transfer, releasing the line if we were driving it.
+    pub(crate) fn abort_poll(&mut self) {
+        let state = core::mem::replace(&mut self.poller.state, PollState::Idle);
+        if let PollState::Tx { pin, .. } = state {
+            let pin = self.release_pin(pin);
+            self.bring_back_pin(pin);
+        }
+        self.poller.due = None;
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Unit,
+    V: WireObserver,
+{
+    /// Queues `data` for `poll`. Fails with `Error::Busy` while a polled
+    /// transfer is in progress.
+    pub fn start_write(&mut self, data: u8) -> Result<(), Error> {
+        if !self.may_transmit() {
+            return Err(Error::Unavailable);
+        }
+        if self.config.encoding != Encoding::PulseWidth {
+            return Err(Error::Unavailable);
+        }
+        if !matches!(self.poller.state, PollState::Idle) {
+            return Err(Error::Busy);
+        }
+
+        self.poller.state = PollState::Gap {
+            data: data,
+            idle: 0,
+        };
+        return Ok(());
+    }
+
+    /// Byte of the last `WireEvent::FrameReceived` reported by `poll`.
+    pub fn take_received(&mut self) -> Option<u8> {
+        return self.poller.received.take();
+    }
+
+    /// Advances polled transfers to `now`. Has to be called at least once per
+    /// phase; calls before the next phase is due return `None` right away.
+    /// Only pulse-width coding is supported; with any other `Config::encoding`
+    /// nothing happens.
+    pub fn poll(&mut self, now: Ticks) -> Option<WireEvent> {
+        if self.config.encoding != Encoding::PulseWidth {
+            return None;
+        }
+        if let Some(due) = self.poller.due {
+            if (now.wrapping_sub(due) as i32) < 0 {
+                return None;
+            }
+        }
+        // Deadlines are compared by signed distance, so a phase may take at
+        // most half the range of `now`.
+        let phase = self
+            .delay
+            .as_micros()
+            .saturating_mul(self.timing.ticks_per_phase as u32)
+            .min(i32::MAX as u32);
+        self.poller.due = Some(now.wrapping_add(phase));
+
+        let state = core::mem::replace(&mut self.poller.state, PollState::Idle);
+        let (state, event) = match self.step(state) {
+            Ok(s) => s,
+            Err(e) => (PollState::Idle, self.polled_error(e)),
+        };
+        self.poller.state = state;
+        return event;
+    }
+
+    fn step(&mut self, state: PollState<O>) -> Result<(PollState<O>, Option<WireEvent>), Error> {
+        let line_low = match &self.pin {
+            Some(s) => io_err!(s.is_low())?,
+            None => false,
+        };
+
+        match state {
+            PollState::Idle if line_low => return Ok((self.start_polled_rx(), None)),
+            PollState::Idle => return Ok((PollState::Idle, None)),
+            // Someone else started first: drop our write and receive theirs.
+            PollState::Gap { .. } if line_low => {
+                let event = self
+                    .settle(Err::<(), _>(Error::Busy))
+                    .err()
+                    .map(WireEvent::from);
+                return Ok((self.start_polled_rx(), event));
+            }
+            PollState::Gap { data, idle } => {
+                if idle + 1 < self.config.frame_gap {
+                    let gap = PollState::Gap {
+                        data: data,
+                        idle: idle + 1,
+                    };
+                    return Ok((gap, None));
+                }
+
+                let pin = match self.pin.take() {
+                    Some(s) => s,
+                    None => return Err(Error::Unavailable),
+                };
+                let pin = self.drive_pin(pin);
+
+                self.activity = Activity::Transmitting;
+                self.inflight = data;
+                self.bits = 0;
+                let mut scrambler = self.tx_scrambler;
+                let ordered = self.config.bit_order.apply(data);
+                let symbol = if self.config.scramble {
+                    scrambler.scramble(ordered)
+                } else {
+                    ordered
+                };
+                let encoder = coding::encode_word(&self.config, symbol as u32, 8);
+                return Ok((self.next_segment(pin, data, encoder, 0), None));
+            }
+            PollState::Tx {
+                pin,
+                data,
+                encoder,
+                begun,
+                left,
+            } if left > 0 => {
+                let tx = PollState::Tx {
+                    pin: pin,
+                    data: data,
+                    encoder: encoder,
+                    begun: begun,
+                    left: left - 1,
+                };
+                return Ok((tx, None));
+            }
+            PollState::Tx {
+                pin,
+                data,
+                encoder,
+                begun,
+                ..
+            } => {
+                return match self.next_segment(pin, data, encoder, begun) {
+                    PollState::Idle => {
+                        if self.config.scramble {
+                            self.tx_scrambler
+                                .scramble(self.config.bit_order.apply(data));
+                        }
+                        self.bits = 8;
+                        self.stats.frames_sent = self.stats.frames_sent.wrapping_add(1);
+                        self.observer.on_frame(Direction::Tx, &[data]);
+                        self.busy_streak = 0;
+                        self.settle(Ok(()))?;
+                        Ok((PollState::Idle, Some(WireEvent::FrameSent { len: 1 })))
+                    }
+                    tx => Ok((tx, None)),
+                };
+            }
+            PollState::Rx {
+                mut decoder,
+                mut low,
+                mut high,
+            } => {
+                if line_low {
+                    if high > 0 {
+                        let eighths = high as u32 * 8;
+                        let pulse = coding::classify(&self.config.pulse_bands, eighths)?;
+                        if let Pulse::Bit(bit) = pulse {
+                            self.observer.on_bit(Direction::Rx, bit);
+                        }
+                        decoder.push(pulse)?;
+                        self.inflight = decoder.word() as u8;
+                        self.bits = decoder.bits();
+                        high = 0;
+                    }
+                    low = low.saturating_add(1);
+
+                    if let Some(limit) = self.config.bit_timeout {
+                        if decoder.bits() > 0 && low as u16 > limit {
+                            return Err(Error::Truncated {
+                                bits_received: decoder.bits(),
+                            });
+                        }
+                    }
+                } else {
+                    if low > 0 {
+                        decoder.feed(false, low as u32 * 8)?;
+                        // A start condition too short for the decoder is a glitch.
+                        if !decoder.in_frame() {
+                            self.activity = Activity::Idle;
+                            return Ok((PollState::Idle, None));
+                        }
+                    }
+                    low = 0;
+                    high = high.saturating_add(1);
+
+                    let eighths = high as u32 * 8;
+                    if eighths > self.config.pulse_bands.stop as u32 {
+                        let word = decoder.finish()?;
+                        let event = self.finish_polled_rx(word as u8)?;
+                        return Ok((PollState::Idle, event));
+                    }
+                }
+
+                let rx = PollState::Rx {
+                    decoder: decoder,
+                    low: low,
+                    high: high,
+                };
+                return Ok((rx, None));
+            }
+        }
+    }
+
+    /// Drives the next segment of a polled write, or releases the line and
+    /// returns `PollState::Idle` after the last one.
+    fn next_segment(
+        &mut self,
+        mut pin: O,
+        data: u8,
+        mut encoder: Encoder,
+        mut begun: u8,
+    ) -> PollState<O> {
+        let segment = match encoder.next() {
+            Some(s) => s,
+            None => {
+                let pin = self.release_pin(pin);
+                self.bring_back_pin(pin);
+                return PollState::Idle;
+            }
+        };
+
+        if segment.high {
+            pin.set_high().ok();
+        } else {
+            pin.set_low().ok();
+        }
+        if let Some(bit) = segment.bit {
+            // The bit before this one is complete.
+            self.bits = begun.min(8);
+            begun = begun.saturating_add(1);
+            self.observer.on_bit(Direction::Tx, bit);
+        }
+        return PollState::Tx {
+            pin: pin,
+            data: data,
+            encoder: encoder,
+            begun: begun,
+            left: segment.phases.saturating_sub(1),
+        };
+    }
+
+    fn polled_error(&mut self, e: Error) -> Option<WireEvent> {
+        self.settle::<()>(Err(e)).ok();
+        return Some(e.into());
+    }
+
+    fn start_polled_rx(&mut self) -> PollState<O> {
+        self.activity = Activity::Receiving;
+        self.inflight = 0;
+        self.bits = 0;
+        return PollState::Rx {
+            decoder: Decoder::new(&self.config),
+            low: 1,
+            high: 0,
+        };
+    }
+
+    fn finish_polled_rx(&mut self, symbol: u8) -> Result<Option<WireEvent>, Error> {
+        let mut data = [0u8; 1];
+        self.deliver_rx(symbol as u32, &mut data);
+        self.poller.received = Some(data[0]);
+        self.settle(Ok(()))?;
+        return Ok(Some(WireEvent::FrameReceived { len: 1 }));
+    }
+}
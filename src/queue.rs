@@ -0,0 +1,92 @@
+//! Deferred transmission: bytes are queued while the application runs and
+//! sent in one go by `flush`, which rides out a busy line by itself.
+use crate::observer::WireObserver;
+use crate::ring::Ring;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub struct TxQueue<const N: usize> {
+    bytes: Ring<u8, N>,
+}
+
+impl<const N: usize> TxQueue<N> {
+    pub const fn new() -> Self {
+        TxQueue { bytes: Ring::new() }
+    }
+
+    /// Fails with `Error::Busy` while the queue is full.
+    pub fn enqueue(&mut self, byte: u8) -> Result<(), Error> {
+        return self.bytes.push(byte).map_err(|_| Error::Busy);
+    }
+
+    /// Queues all of `data` or nothing: `Error::FrameTooLarge` if it can
+    /// never fit, `Error::Busy` if it does not fit right now.
+    pub fn enqueue_frame(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > N {
+            return Err(Error::FrameTooLarge);
+        }
+        if data.len() > N - self.bytes.len() {
+            return Err(Error::Busy);
+        }
+
+        for &byte in data {
+            self.bytes.push(byte).ok();
+        }
+        return Ok(());
+    }
+
+    pub fn len(&self) -> usize {
+        return self.bytes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.bytes.len() == 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    /// Sends the queue in order and returns how many bytes went out. A busy
+    /// line is waited out up to `Config::retries` times in a row; after that,
+    /// or on any other error, the unsent bytes stay queued.
+    pub fn flush<F2, F1, I, O, T, V>(
+        &mut self,
+        wire: &mut HalfDuplexWire<F2, F1, I, O, T, V>,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<usize, Error>
+    where
+        F1: Fn(O) -> I,
+        F2: Fn(I) -> O,
+        I: InputPin,
+        O: OutputPin,
+        T: Copy,
+        V: WireObserver,
+    {
+        let mut sent = 0;
+        let mut busy = 0;
+
+        while let Some(byte) = self.bytes.peek() {
+            match wire.write(byte, delay) {
+                Ok(()) => {
+                    self.bytes.pop();
+                    sent += 1;
+                    busy = 0;
+                }
+                Err(Error::Busy) if busy < wire.config().retries => {
+                    busy += 1;
+                    wire.wait_retry_gap(delay);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        return Ok(sent);
+    }
+}
+
+impl<const N: usize> Default for TxQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,37 @@
+//! Getting both ends of a confused link back to idle. A receiver stuck in
+//! the middle of a frame takes the break as one long bit and ends the frame
+//! when the line is released; a receiver waiting for a start condition sees
+//! it as a break. Either way both sides are idle afterwards.
+use crate::observer::WireObserver;
+use crate::scrambler::Scrambler;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire, BREAK_PHASES};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Resets the local receive and transmit state, lets a transmission in
+    /// progress finish, then sends a break and waits until the line has been
+    /// idle as long again. Fails with `Error::Busy` if the line is held low
+    /// throughout.
+    pub fn recover(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        self.abort_poll();
+        self.tx_scrambler = Scrambler::new();
+        self.rx_scrambler = Scrambler::new();
+        self.link.rx_seq = None;
+        self.busy_streak = 0;
+        self.inflight = 0;
+        self.bits = 0;
+
+        self.wait_bus_free(1, 4 * BREAK_PHASES, delay)?;
+        self.send_break(BREAK_PHASES, delay)?;
+        return self.wait_bus_free(BREAK_PHASES, 4 * BREAK_PHASES, delay);
+    }
+}
@@ -0,0 +1,172 @@
+//! A register map on top of packets, like an I²C EEPROM: the slave backs it
+//! with a byte slice, the master reads and writes ranges of it.
+//!
+//! ```text
+//! request:  | READ_REG  | reg | len |
+//!           | WRITE_REG | reg | len | data (len bytes) |
+//! reply:    | status | data (len bytes, reads only) |
+//! ```
+//!
+//! Requests and replies are ordinary data packets, so both are acked and
+//! retried. A status other than `STATUS_OK` means the request was malformed
+//! or its range did not fit the map, and nothing was accessed.
+use crate::crc::CrcEngine;
+use crate::observer::WireObserver;
+use crate::packet::recv_packet_within;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const READ_REG: u8 = 0x01;
+pub const WRITE_REG: u8 = 0x02;
+pub const STATUS_OK: u8 = 0x00;
+pub const STATUS_OUT_OF_RANGE: u8 = 0x01;
+/// Too short, an unknown command, or data not matching the length.
+pub const STATUS_BAD_REQUEST: u8 = 0x02;
+/// Longest range one request can access.
+pub const MAX_REG_LEN: usize = 32;
+/// Receive buffer a slave needs for any request.
+pub const REQUEST_LEN: usize = 3 + MAX_REG_LEN;
+
+/// What `serve_registers` did, so the application can react to writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterAccess {
+    Read {
+        reg: u8,
+        len: u8,
+    },
+    Write {
+        reg: u8,
+        len: u8,
+    },
+    /// Out of range or malformed; answered with an error status.
+    Rejected,
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Reads `buf.len()` registers starting at `reg`.
+    pub fn read_registers<C: CrcEngine>(
+        &mut self,
+        reg: u8,
+        buf: &mut [u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        if buf.len() > MAX_REG_LEN {
+            return Err(Error::FrameTooLarge);
+        }
+
+        self.send_packet(&[READ_REG, reg, buf.len() as u8], crc, delay)?;
+
+        let mut reply = [0u8; 1 + MAX_REG_LEN];
+        let packet = self.recv_reply(&mut reply, crc, delay)?;
+        if packet.len() != 1 + buf.len() {
+            return self.settle(Err(Error::Framing));
+        }
+        buf.copy_from_slice(&packet[1..]);
+        return Ok(());
+    }
+
+    /// Writes `data` to the registers starting at `reg`.
+    pub fn write_registers<C: CrcEngine>(
+        &mut self,
+        reg: u8,
+        data: &[u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        if data.len() > MAX_REG_LEN {
+            return Err(Error::FrameTooLarge);
+        }
+
+        let mut request = [0u8; REQUEST_LEN];
+        request[..3].copy_from_slice(&[WRITE_REG, reg, data.len() as u8]);
+        request[3..3 + data.len()].copy_from_slice(data);
+        self.send_packet(&request[..3 + data.len()], crc, delay)?;
+
+        let mut reply = [0u8; 1];
+        self.recv_reply(&mut reply, crc, delay)?;
+        return Ok(());
+    }
+
+    /// Waits for the slave's reply and checks its status.
+    fn recv_reply<'b, C: CrcEngine>(
+        &mut self,
+        buf: &'b mut [u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<&'b [u8], Error> {
+        let timeout = Some(self.config.response_timeout);
+        let packet = recv_packet_within(self, timeout, buf, crc, delay)?;
+
+        return match packet.payload.first() {
+            Some(&STATUS_OK) => Ok(packet.payload),
+            _ => self.settle(Err(Error::Framing)),
+        };
+    }
+
+    /// Answers one request against `regs`. `buf` receives the request and
+    /// should hold `REQUEST_LEN` bytes.
+    pub fn serve_registers<C: CrcEngine>(
+        &mut self,
+        regs: &mut [u8],
+        buf: &mut [u8],
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<RegisterAccess, Error> {
+        let packet = self.recv_packet(buf, crc, delay)?;
+        let (command, reg, len, data) = match packet.payload {
+            [command, reg, len, data @ ..] => (*command, *reg, *len, data),
+            _ => return self.reject(STATUS_BAD_REQUEST, crc, delay),
+        };
+        let well_formed = match command {
+            READ_REG => data.is_empty(),
+            WRITE_REG => data.len() == len as usize,
+            _ => false,
+        };
+        if !well_formed {
+            return self.reject(STATUS_BAD_REQUEST, crc, delay);
+        }
+
+        let start = reg as usize;
+        let end = start + len as usize;
+        if end > regs.len() || len as usize > MAX_REG_LEN {
+            return self.reject(STATUS_OUT_OF_RANGE, crc, delay);
+        }
+
+        match command {
+            READ_REG => {
+                let mut reply = [0u8; 1 + MAX_REG_LEN];
+                reply[0] = STATUS_OK;
+                reply[1..1 + len as usize].copy_from_slice(&regs[start..end]);
+                self.send_packet(&reply[..1 + len as usize], crc, delay)?;
+                return Ok(RegisterAccess::Read { reg: reg, len: len });
+            }
+            _ => {
+                regs[start..end].copy_from_slice(data);
+                self.send_packet(&[STATUS_OK], crc, delay)?;
+                return Ok(RegisterAccess::Write { reg: reg, len: len });
+            }
+        }
+    }
+
+    /// Answers with the error `status`, so the master does not have to wait
+    /// for its timeout.
+    fn reject<C: CrcEngine>(
+        &mut self,
+        status: u8,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<RegisterAccess, Error> {
+        self.send_packet(&[status], crc, delay)?;
+        return Ok(RegisterAccess::Rejected);
+    }
+}
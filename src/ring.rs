@@ -0,0 +1,53 @@
+//! Fixed-capacity FIFO shared by the queues of this crate.
+
+pub(crate) struct Ring<T, const N: usize> {
+    items: [Option<T>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+    pub(crate) const fn new() -> Self {
+        Ring {
+            items: [None; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Hands `item` back if the ring is full.
+    pub(crate) fn push(&mut self, item: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(item);
+        }
+        self.items[(self.head + self.len) % N] = Some(item);
+        self.len += 1;
+        return Ok(());
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let item = self.items[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        return item;
+    }
+
+    pub(crate) fn peek(&self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        return self.items[self.head];
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        while self.pop().is_some() {}
+        self.head = 0;
+    }
+}
@@ -0,0 +1,44 @@
+//! Self-synchronizing scrambler with polynomial x^7 + x^6 + 1. Whitening
+//! breaks up long runs of identical bits, which spreads the emitted spectrum.
+//! The descrambler locks onto the sender after seven bits, so a lost frame
+//! garbles at most the start of the next one.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Scrambler {
+    state: u8,
+}
+
+impl Scrambler {
+    pub const fn new() -> Self {
+        Scrambler { state: 0 }
+    }
+
+    fn feedback(&self) -> u8 {
+        return (self.state >> 6 ^ self.state >> 5) & 1;
+    }
+
+    fn shift(&mut self, bit: u8) {
+        self.state = (self.state << 1 | bit) & 0x7f;
+    }
+
+    /// Scrambles `data` in wire order, most significant bit first.
+    pub fn scramble(&mut self, data: u8) -> u8 {
+        let mut out = 0u8;
+        for i in (0..8).rev() {
+            let bit = (data >> i & 1) ^ self.feedback();
+            self.shift(bit);
+            out |= bit << i;
+        }
+        return out;
+    }
+
+    pub fn descramble(&mut self, data: u8) -> u8 {
+        let mut out = 0u8;
+        for i in (0..8).rev() {
+            let bit = data >> i & 1;
+            out |= (bit ^ self.feedback()) << i;
+            self.shift(bit);
+        }
+        return out;
+    }
+}
@@ -0,0 +1,154 @@
+//! Authenticated, encrypted frames with the `secure` feature, for links that
+//! leave the board. Every frame is sealed with ChaCha20-Poly1305 under a
+//! pre-shared key and sent with `send_frame`:
+//!
+//! ```text
+//! | counter (8 bytes) | ciphertext | tag (16 bytes) |
+//! ```
+//!
+//! The nonce is the sender's id followed by its counter, so both ends can use
+//! the same key without ever reusing a nonce. The receiver only accepts
+//! counters above the last one it authenticated, which turns a recorded and
+//! replayed frame into `Error::Replay`.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use chacha20poly1305::aead::AeadInPlace;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce, Tag};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const COUNTER_LEN: usize = 8;
+pub const TAG_LEN: usize = 16;
+/// Largest plaintext that still fits a frame.
+pub const MAX_SECURE_PAYLOAD: usize = u8::MAX as usize - COUNTER_LEN - TAG_LEN;
+
+pub struct SecureLink {
+    cipher: ChaCha20Poly1305,
+    local_id: u32,
+    peer_id: u32,
+    tx_counter: u64,
+    rx_counter: Option<u64>,
+}
+
+fn nonce(id: u32, counter: u64) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[..4].copy_from_slice(&id.to_be_bytes());
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    return nonce;
+}
+
+impl SecureLink {
+    /// A link to `peer_id` under `key`. The ids have to differ, and every
+    /// device sharing the key needs its own.
+    pub fn new(key: &[u8; 32], local_id: u32, peer_id: u32) -> Self {
+        SecureLink {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            local_id: local_id,
+            peer_id: peer_id,
+            tx_counter: 0,
+            rx_counter: None,
+        }
+    }
+
+    /// Counters to continue from, e.g. after a reset that kept them in
+    /// non-volatile memory. Starting over with the same key lets a tap replay
+    /// every earlier frame.
+    pub fn resume(&mut self, tx_counter: u64, rx_counter: Option<u64>) {
+        self.tx_counter = tx_counter;
+        self.rx_counter = rx_counter;
+    }
+
+    pub fn tx_counter(&self) -> u64 {
+        return self.tx_counter;
+    }
+
+    pub fn rx_counter(&self) -> Option<u64> {
+        return self.rx_counter;
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Seals `data`, at most `MAX_SECURE_PAYLOAD` bytes, and sends it as one
+    /// frame. The counter moves on even if sending fails.
+    pub fn send_secure(
+        &mut self,
+        link: &mut SecureLink,
+        data: &[u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        if data.len() > MAX_SECURE_PAYLOAD {
+            return Err(Error::FrameTooLarge);
+        }
+
+        let counter = link.tx_counter;
+        link.tx_counter = match counter.checked_add(1) {
+            Some(c) => c,
+            None => return Err(Error::Unavailable),
+        };
+
+        let mut frame = [0u8; u8::MAX as usize];
+        let end = COUNTER_LEN + data.len();
+        frame[..COUNTER_LEN].copy_from_slice(&counter.to_be_bytes());
+        frame[COUNTER_LEN..end].copy_from_slice(data);
+
+        let tag = link
+            .cipher
+            .encrypt_in_place_detached(
+                &nonce(link.local_id, counter),
+                &[],
+                &mut frame[COUNTER_LEN..end],
+            )
+            .map_err(|_| Error::Unavailable)?;
+        frame[end..end + TAG_LEN].copy_from_slice(&tag);
+
+        return self.send_frame(&frame[..end + TAG_LEN], delay);
+    }
+
+    /// Receives a sealed frame, checks and opens it into `buf` and returns
+    /// the plaintext length. Forged or corrupted frames fail with
+    /// `Error::Unauthenticated`, old ones with `Error::Replay`.
+    pub fn recv_secure(
+        &mut self,
+        link: &mut SecureLink,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<usize, Error> {
+        let mut frame = [0u8; u8::MAX as usize];
+        let len = self.recv_frame(&mut frame, delay)?;
+        if len < COUNTER_LEN + TAG_LEN {
+            return self.settle(Err(Error::Unauthenticated));
+        }
+
+        let end = len - TAG_LEN;
+        let mut bytes = [0u8; COUNTER_LEN];
+        bytes.copy_from_slice(&frame[..COUNTER_LEN]);
+        let counter = u64::from_be_bytes(bytes);
+        if link.rx_counter.is_some_and(|last| counter <= last) {
+            return self.settle(Err(Error::Replay));
+        }
+        if end - COUNTER_LEN > buf.len() {
+            return self.settle(Err(Error::FrameTooLarge));
+        }
+
+        let tag = *Tag::from_slice(&frame[end..len]);
+        let body = &mut frame[COUNTER_LEN..end];
+        let opened =
+            link.cipher
+                .decrypt_in_place_detached(&nonce(link.peer_id, counter), &[], body, &tag);
+        if opened.is_err() {
+            return self.settle(Err(Error::Unauthenticated));
+        }
+
+        link.rx_counter = Some(counter);
+        buf[..body.len()].copy_from_slice(body);
+        return Ok(body.len());
+    }
+}
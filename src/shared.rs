@@ -0,0 +1,168 @@
+//! Several logical drivers on one physical wire. The wire is parked in a
+//! critical-section mutex and checked out for the duration of a single
+//! operation, so a proxy that finds it missing gets `Error::Busy` instead of
+//! blocking an interrupt priority.
+use crate::config::Config;
+use crate::units::PhaseDelay;
+use crate::{Error, ReadWrite};
+use core::cell::{Cell, RefCell};
+use critical_section::Mutex;
+
+pub struct SharedWire<W> {
+    wire: Mutex<RefCell<Option<W>>>,
+    /// The wire's settings the last time a proxy had it.
+    config: Mutex<Cell<Option<Config>>>,
+}
+
+impl<W> SharedWire<W> {
+    pub const fn new(wire: W) -> Self {
+        SharedWire {
+            wire: Mutex::new(RefCell::new(Some(wire))),
+            config: Mutex::new(Cell::new(None)),
+        }
+    }
+
+    pub fn proxy(&self) -> WireProxy<'_, W> {
+        WireProxy { bus: self }
+    }
+
+    /// Runs `f` with exclusive access to the wire.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut W) -> R) -> Result<R, Error> {
+        let mut wire = match critical_section::with(|cs| self.wire.borrow(cs).borrow_mut().take()) {
+            Some(s) => s,
+            None => return Err(Error::Busy),
+        };
+
+        let result = f(&mut wire);
+
+        critical_section::with(|cs| *self.wire.borrow(cs).borrow_mut() = Some(wire));
+        return Ok(result);
+    }
+
+    /// Gives the wire back, `None` if a proxy is using it right now.
+    pub fn into_inner(self) -> Option<W> {
+        return self.wire.into_inner().into_inner();
+    }
+}
+
+pub struct WireProxy<'a, W> {
+    bus: &'a SharedWire<W>,
+}
+
+impl<'a, W> Clone for WireProxy<'a, W> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, W> Copy for WireProxy<'a, W> {}
+
+impl<'a, W> WireProxy<'a, W> {
+    pub fn lock<R>(&self, f: impl FnOnce(&mut W) -> R) -> Result<R, Error> {
+        return self.bus.lock(f);
+    }
+
+    /// Like `lock`, and keeps a copy of the wire's settings for `config`.
+    fn with_wire<T, R>(&self, f: impl FnOnce(&mut W) -> R) -> Result<R, Error>
+    where
+        W: ReadWrite<T>,
+    {
+        return self.bus.lock(|wire| {
+            let result = f(wire);
+            let config = wire.config();
+            critical_section::with(|cs| self.bus.config.borrow(cs).set(Some(config)));
+            return result;
+        });
+    }
+}
+
+impl<'a, W, T> ReadWrite<T> for WireProxy<'a, W>
+where
+    W: ReadWrite<T>,
+{
+    fn write(&mut self, data: u8, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return self.with_wire(|wire| wire.write(data, delay))?;
+    }
+
+    fn read(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return self.with_wire(|wire| wire.read(delay))?;
+    }
+
+    fn read_timeout(&mut self, timeout: u16, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return self.with_wire(|wire| wire.read_timeout(timeout, delay))?;
+    }
+
+    /// The wire's settings. While another proxy holds the wire, those it
+    /// had when a proxy last used it; the defaults if none ever did.
+    fn config(&self) -> Config {
+        if let Ok(config) = self.with_wire(|wire| wire.config()) {
+            return config;
+        }
+        let seen = critical_section::with(|cs| self.bus.config.borrow(cs).get());
+        return seen.unwrap_or_default();
+    }
+
+    fn settle<R>(&mut self, result: Result<R, Error>) -> Result<R, Error> {
+        let mut result = Some(result);
+        return match self.with_wire(|wire| wire.settle(result.take().unwrap())) {
+            Ok(settled) => settled,
+            Err(_) => result.take().unwrap(),
+        };
+    }
+
+    fn transfer_in_place(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        return self.with_wire(|wire| wire.transfer_in_place(buf, delay))?;
+    }
+}
+
+impl<W> SharedWire<W> {
+    /// Splits access into a transmit and a receive half, e.g. for an idle
+    /// task and a GPIO interrupt. Whichever half is mid-operation owns the
+    /// pin; the other one gets `Error::Busy` meanwhile.
+    pub fn split(&self) -> (WireTx<'_, W>, WireRx<'_, W>) {
+        return (WireTx { bus: self }, WireRx { bus: self });
+    }
+}
+
+pub struct WireTx<'a, W> {
+    bus: &'a SharedWire<W>,
+}
+
+impl<'a, W> WireTx<'a, W> {
+    pub fn write<T>(&mut self, data: u8, delay: &mut impl PhaseDelay<T>) -> Result<(), Error>
+    where
+        W: ReadWrite<T>,
+    {
+        return self.bus.lock(|wire| wire.write(data, delay))?;
+    }
+
+    /// Sends all of `data` without letting the receive half in between.
+    pub fn write_all<T>(&mut self, data: &[u8], delay: &mut impl PhaseDelay<T>) -> Result<(), Error>
+    where
+        W: ReadWrite<T>,
+    {
+        return self.bus.lock(|wire| {
+            for &byte in data {
+                wire.write(byte, delay)?;
+            }
+            return Ok(());
+        })?;
+    }
+}
+
+pub struct WireRx<'a, W> {
+    bus: &'a SharedWire<W>,
+}
+
+impl<'a, W> WireRx<'a, W> {
+    pub fn read<T>(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u8, Error>
+    where
+        W: ReadWrite<T>,
+    {
+        return self.bus.lock(|wire| wire.read(delay))?;
+    }
+}
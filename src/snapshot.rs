@@ -0,0 +1,68 @@
+//! Plain-data capture of the driver state, meant to be written to noinit RAM
+//! from a panic handler and inspected after the reset.
+use crate::observer::WireObserver;
+use crate::stats::Stats;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+pub const SNAPSHOT_MAGIC: u32 = 0x4844_5731;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activity {
+    Idle = 0,
+    Transmitting = 1,
+    Receiving = 2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// `SNAPSHOT_MAGIC` when the record was written by `freeze`.
+    pub magic: u32,
+    pub activity: u8,
+    /// `Role` the driver was in.
+    pub role: u8,
+    /// `Error::code` of the last failure, 0 if nothing failed yet.
+    pub last_error: u8,
+    /// Byte being sent, or the bits assembled so far while receiving.
+    pub inflight: u8,
+    /// Bits of `inflight` already on the wire.
+    pub bits: u8,
+    /// 0 while the pin is checked out, i.e. an operation was interrupted.
+    pub pin_available: u8,
+    pub stats: Stats,
+}
+
+impl Snapshot {
+    pub fn is_valid(&self) -> bool {
+        return self.magic == SNAPSHOT_MAGIC;
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn freeze(&self) -> Snapshot {
+        Snapshot {
+            magic: SNAPSHOT_MAGIC,
+            activity: self.activity as u8,
+            role: self.role as u8,
+            last_error: self.last_error.map(|e| e.code()).unwrap_or(0),
+            inflight: self.inflight,
+            bits: self.bits,
+            pin_available: self.pin.is_some() as u8,
+            stats: self.stats,
+        }
+    }
+
+    pub fn last_error(&self) -> Option<Error> {
+        return self.last_error;
+    }
+}
@@ -0,0 +1,46 @@
+//! Link-quality counters. They wrap around instead of saturating, so deltas
+//! between two telemetry reports stay correct.
+use crate::observer::WireObserver;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub frames_sent: u32,
+    pub frames_received: u32,
+    pub crc_errors: u32,
+    pub timeouts: u32,
+    /// Transmissions abandoned because the line was not idle.
+    pub collisions: u32,
+    pub retries: u32,
+}
+
+impl Stats {
+    pub(crate) fn record(&mut self, e: Error) {
+        match e {
+            Error::Crc => self.crc_errors = self.crc_errors.wrapping_add(1),
+            Error::Busy => self.collisions = self.collisions.wrapping_add(1),
+            Error::Timeout => self.timeouts = self.timeouts.wrapping_add(1),
+            _ => {}
+        }
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn stats(&self) -> &Stats {
+        return &self.stats;
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+}
@@ -0,0 +1,107 @@
+//! Tearing a wire down for deep sleep and rebuilding it after wake. The
+//! `WireState` keeps everything the peer relies on: configuration, the
+//! calibrated timing, packet sequence numbers, scrambler state and the
+//! token, so the link goes on where it stopped without a new `connect` or
+//! `calibrate`. It is plain data; keep it in memory that survives the
+//! sleep, e.g. backup RAM.
+//!
+//! The observer and the pull-up hook are not part of the state, attach them
+//! again after `resume`.
+use crate::backoff::Backoff;
+use crate::config::{Config, Role, Timing};
+use crate::handshake::Capabilities;
+use crate::observer::WireObserver;
+use crate::packet::Link;
+use crate::scrambler::Scrambler;
+use crate::stats::Stats;
+use crate::token::Token;
+use crate::units::Unit;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WireState {
+    config: Config,
+    timing: Timing,
+    low_power: bool,
+    role: Role,
+    link: Link,
+    tx_scrambler: Scrambler,
+    rx_scrambler: Scrambler,
+    token: Token,
+    token_requested: bool,
+    backoff: Backoff,
+    peer_caps: Option<Capabilities>,
+    stats: Stats,
+}
+
+impl WireState {
+    pub fn config(&self) -> &Config {
+        return &self.config;
+    }
+
+    pub fn stats(&self) -> Stats {
+        return self.stats;
+    }
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    /// Stops any polled transfer and hands back the pin with the state to
+    /// `resume` from. Fails with `Error::Unavailable` if the pin is checked
+    /// out.
+    pub fn suspend(mut self) -> Result<(I, WireState), Error> {
+        self.abort_poll();
+        let state = WireState {
+            config: self.config,
+            timing: self.timing,
+            low_power: self.low_power,
+            role: self.role,
+            link: self.link,
+            tx_scrambler: self.tx_scrambler,
+            rx_scrambler: self.rx_scrambler,
+            token: self.token,
+            token_requested: self.token_requested,
+            backoff: self.backoff,
+            peer_caps: self.peer_caps,
+            stats: self.stats,
+        };
+        let pin = self.release()?;
+        return Ok((pin, state));
+    }
+}
+
+impl<F2, F1, I, O, T> HalfDuplexWire<F2, F1, I, O, T>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Unit,
+{
+    /// Like `new`, continuing from `state`. Pin and delay have to be the
+    /// same as before the suspend.
+    pub fn resume(pin: I, into_output: F2, into_input: F1, delay: T, state: WireState) -> Self {
+        let mut wire = HalfDuplexWire::new(pin, into_output, into_input, delay);
+        wire.config = state.config;
+        wire.timing = state.timing;
+        wire.low_power = state.low_power;
+        wire.role = state.role;
+        wire.link = state.link;
+        wire.tx_scrambler = state.tx_scrambler;
+        wire.rx_scrambler = state.rx_scrambler;
+        wire.token = state.token;
+        wire.token_requested = state.token_requested;
+        wire.backoff = state.backoff;
+        wire.peer_caps = state.peer_caps;
+        wire.stats = state.stats;
+        return wire;
+    }
+}
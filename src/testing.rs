@@ -0,0 +1,290 @@
+//! A simulated line for host tests, with the `testing` feature. `Line` plays
+//! the wire itself plus the peer: the peer's levels are scripted ahead of
+//! time, everything our side drives is decoded back into bits. Time is
+//! simulated too; it advances with every delay and every pin poll, so the
+//! busy-wait loops of the receiver make progress.
+//!
+//! ```
+//! # use half_duplex_wire::testing::{mock_wire, Line};
+//! let line = Line::new(10);
+//! let mut wire = mock_wire(&line);
+//! let mut delay = line.delay();
+//!
+//! wire.write(0xa5, &mut delay).unwrap();
+//! let mut bits = [false; 8];
+//! assert_eq!(line.written_bits(&mut bits), 8);
+//!
+//! line.script_byte(0x3c);
+//! assert_eq!(wire.read(&mut delay).unwrap(), 0x3c);
+//! ```
+use crate::manchester::MANCHESTER_HALF;
+use crate::units::Micros;
+use crate::HalfDuplexWire;
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Scripted peer segments that can be queued at once.
+pub const SCRIPT_LEN: usize = 1024;
+/// Bits of our own output that are kept for `written_bits`.
+pub const WRITTEN_LEN: usize = 512;
+
+#[derive(Clone, Copy)]
+struct Segment {
+    until: u32,
+    low: bool,
+}
+
+pub struct Line {
+    phase: u32,
+    now: Cell<u32>,
+    /// We hold the line low.
+    driven: Cell<bool>,
+    /// When our output last went high.
+    rose: Cell<Option<u32>>,
+    script: RefCell<[Segment; SCRIPT_LEN]>,
+    script_len: Cell<usize>,
+    script_pos: Cell<usize>,
+    written: RefCell<[bool; WRITTEN_LEN]>,
+    written_len: Cell<usize>,
+}
+
+impl Line {
+    /// A released line with phases of `phase_us` microseconds.
+    pub fn new(phase_us: u32) -> Self {
+        Line {
+            phase: phase_us,
+            now: Cell::new(0),
+            driven: Cell::new(false),
+            rose: Cell::new(None),
+            script: RefCell::new(
+                [Segment {
+                    until: 0,
+                    low: false,
+                }; SCRIPT_LEN],
+            ),
+            script_len: Cell::new(0),
+            script_pos: Cell::new(0),
+            written: RefCell::new([false; WRITTEN_LEN]),
+            written_len: Cell::new(0),
+        }
+    }
+
+    pub fn now(&self) -> u32 {
+        return self.now.get();
+    }
+
+    pub fn advance(&self, us: u32) {
+        self.now.set(self.now.get().wrapping_add(us));
+    }
+
+    pub fn input(&self) -> LineInput<'_> {
+        return LineInput { line: self };
+    }
+
+    pub fn delay(&self) -> LineDelay<'_> {
+        return LineDelay { line: self };
+    }
+
+    pub fn is_low(&self) -> bool {
+        return self.driven.get() || self.peer_low();
+    }
+
+    fn peer_low(&self) -> bool {
+        let script = self.script.borrow();
+        let mut pos = self.script_pos.get();
+        while pos < self.script_len.get() && script[pos].until <= self.now.get() {
+            pos += 1;
+        }
+        self.script_pos.set(pos);
+
+        return pos < self.script_len.get() && script[pos].low;
+    }
+
+    /// Lets the peer hold the line at one level for `phases` phases, after
+    /// whatever is scripted already, or from now on if that is over.
+    pub fn script(&self, low: bool, phases: u32) {
+        self.peer_low();
+        let mut script = self.script.borrow_mut();
+        // Segments that are over make room for new ones.
+        let pos = self.script_pos.get();
+        let len = self.script_len.get() - pos;
+        script.copy_within(pos..pos + len, 0);
+        self.script_pos.set(0);
+        assert!(len < SCRIPT_LEN, "line script full");
+
+        let start = if len > 0 {
+            script[len - 1].until
+        } else {
+            self.now.get()
+        };
+        script[len] = Segment {
+            until: start + phases * self.phase,
+            low: low,
+        };
+        self.script_len.set(len + 1);
+    }
+
+    /// Scripts a frame of the low `bits` bits of `word` with a start
+    /// condition of `start_phases`, as the wire would send it, and the idle
+    /// time the receiver needs to see its end.
+    pub fn script_frame(&self, word: u32, bits: u8, start_phases: u8) {
+        self.script(true, start_phases as u32);
+        for i in (0..bits).rev() {
+            let high = if word >> i & 1 != 0 { 4 } else { 2 };
+            self.script(false, high);
+            self.script(true, 8 - high);
+        }
+        self.script(false, 8);
+    }
+
+    /// Like `script_frame`, in Manchester coding with the sync bit.
+    pub fn script_manchester(&self, word: u32, bits: u8, start_phases: u8) {
+        let half = MANCHESTER_HALF as u32;
+        self.script(true, start_phases as u32);
+        self.script(false, half);
+        self.script(true, half);
+        for i in (0..bits).rev() {
+            let one = word >> i & 1 != 0;
+            self.script(one, half);
+            self.script(!one, half);
+        }
+        self.script(false, 8);
+    }
+
+    /// Scripts `byte` for the default configuration.
+    pub fn script_byte(&self, byte: u8) {
+        self.script_frame(byte as u32, 8, 4);
+    }
+
+    /// Copies the bits we sent so far into `out` and returns their count.
+    /// High pulses longer than 3 phases are ones, as on the receiving side.
+    pub fn written_bits(&self, out: &mut [bool]) -> usize {
+        let len = self.written_len.get().min(out.len());
+        out[..len].copy_from_slice(&self.written.borrow()[..len]);
+        return len;
+    }
+
+    pub fn clear_written(&self) {
+        self.written_len.set(0);
+    }
+
+    fn drive(&self, low: bool) {
+        if low && !self.driven.get() {
+            if let Some(rose) = self.rose.take() {
+                self.record(self.now.get() - rose > 3 * self.phase);
+            }
+        }
+        if !low && self.driven.get() {
+            self.rose.set(Some(self.now.get()));
+        }
+        self.driven.set(low);
+    }
+
+    fn release(&self) {
+        self.driven.set(false);
+        self.rose.set(None);
+    }
+
+    fn record(&self, bit: bool) {
+        let len = self.written_len.get();
+        if len < WRITTEN_LEN {
+            self.written.borrow_mut()[len] = bit;
+            self.written_len.set(len + 1);
+        }
+    }
+}
+
+/// Our pin in input mode: the line is released.
+pub struct LineInput<'a> {
+    line: &'a Line,
+}
+
+impl<'a> LineInput<'a> {
+    pub fn into_output(self) -> LineOutput<'a> {
+        return LineOutput { line: self.line };
+    }
+}
+
+impl InputPin for LineInput<'_> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        return self.is_low().map(|low| !low);
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.line.advance(1);
+        return Ok(self.line.is_low());
+    }
+}
+
+/// Our pin driving the line.
+pub struct LineOutput<'a> {
+    line: &'a Line,
+}
+
+impl<'a> LineOutput<'a> {
+    pub fn into_input(self) -> LineInput<'a> {
+        self.line.release();
+        return LineInput { line: self.line };
+    }
+}
+
+impl OutputPin for LineOutput<'_> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.line.drive(true);
+        return Ok(());
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.line.drive(false);
+        return Ok(());
+    }
+}
+
+/// Delays that only advance the simulated time.
+pub struct LineDelay<'a> {
+    line: &'a Line,
+}
+
+macro_rules! line_delay {
+    ($($t:ty),*) => {
+        $(
+            impl DelayUs<$t> for LineDelay<'_> {
+                fn delay_us(&mut self, us: $t) {
+                    self.line.advance(us as u32);
+                }
+            }
+
+            impl DelayMs<$t> for LineDelay<'_> {
+                fn delay_ms(&mut self, ms: $t) {
+                    self.line.advance(ms as u32 * 1000);
+                }
+            }
+        )*
+    };
+}
+
+line_delay!(u8, u16, u32);
+
+pub type MockWire<'a> = HalfDuplexWire<
+    fn(LineInput<'a>) -> LineOutput<'a>,
+    fn(LineOutput<'a>) -> LineInput<'a>,
+    LineInput<'a>,
+    LineOutput<'a>,
+    Micros<u32>,
+>;
+
+/// A wire on `line` with the default configuration, one delay per phase.
+pub fn mock_wire(line: &Line) -> MockWire<'_> {
+    return HalfDuplexWire::new(
+        line.input(),
+        LineInput::into_output,
+        LineOutput::into_input,
+        Micros(line.phase),
+    );
+}
@@ -0,0 +1,100 @@
+//! Token passing: only the side holding the token may transmit and it hands
+//! the token over explicitly after its frame, so both sides can never find
+//! the line idle and start at the same time. The other side asks for the
+//! token with a short request while the holder is listening.
+//!
+//! Enabled with `Config::token_passing`. Exactly one side calls
+//! `claim_token` at start-up; grants are not acked, so the application
+//! should reclaim the token if nothing arrives for a while.
+use crate::config::Role;
+use crate::control::{TOKEN_GRANT, TOKEN_REQUEST};
+use crate::crc::CrcEngine;
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token {
+    Held,
+    Passed,
+    /// Sending a token request or an ack, the only transmissions allowed
+    /// without the token or as a `Role::Listener`.
+    Answering,
+}
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub(crate) fn may_transmit(&self) -> bool {
+        if self.token == Token::Answering {
+            return true;
+        }
+        if self.role == Role::Listener {
+            return false;
+        }
+        return !self.config.token_passing || self.token != Token::Passed;
+    }
+
+    /// Runs `f` with transmissions allowed even without the token or as a
+    /// listener.
+    pub(crate) fn answer<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let token = self.token;
+        self.token = Token::Answering;
+        let result = f(self);
+        if self.token == Token::Answering {
+            self.token = token;
+        }
+        return result;
+    }
+
+    pub fn has_token(&self) -> bool {
+        return self.token == Token::Held;
+    }
+
+    /// Whether the peer asked for the token since we last granted it.
+    pub fn token_requested(&self) -> bool {
+        return self.token_requested;
+    }
+
+    /// Takes the token without asking, for the initial holder or to recover
+    /// from a lost grant.
+    pub fn claim_token(&mut self) {
+        self.token = Token::Held;
+    }
+
+    pub fn request_token<C: CrcEngine>(
+        &mut self,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        if self.token == Token::Held {
+            return Ok(());
+        }
+
+        return self.answer(|wire| wire.send_control(TOKEN_REQUEST, crc, delay));
+    }
+
+    /// Hands the token to the peer; from now on our writes fail with
+    /// `Error::Unavailable` until it is granted back.
+    pub fn grant_token<C: CrcEngine>(
+        &mut self,
+        crc: &mut C,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<(), Error> {
+        if self.token != Token::Held {
+            return Err(Error::Unavailable);
+        }
+
+        self.send_control(TOKEN_GRANT, crc, delay)?;
+        self.token = Token::Passed;
+        self.token_requested = false;
+        return Ok(());
+    }
+}
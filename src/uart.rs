@@ -0,0 +1,98 @@
+//! The same byte interface over a hardware UART in single-wire mode, with TX
+//! and RX on one line. Every byte we send comes back on RX; the backend
+//! reads that echo and drops it, and an echo that differs from what we sent
+//! means someone else was talking at the same time. Frames, checked
+//! transfers, messages and packets run over it as over the wire, without
+//! timeouts: a missing ack blocks until the UART receives something.
+use crate::config::Config;
+use crate::packet::{Link, PacketTransport};
+use crate::units::PhaseDelay;
+use crate::{Error, ReadWrite};
+use embedded_hal::serial;
+
+pub struct UartBackend<S> {
+    serial: S,
+    echo: bool,
+    config: Config,
+    link: Link,
+}
+
+impl<S> UartBackend<S>
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    /// For a UART that hears its own transmissions.
+    pub fn new(serial: S) -> Self {
+        UartBackend {
+            serial: serial,
+            echo: true,
+            config: Config::default(),
+            link: Link::default(),
+        }
+    }
+
+    /// For a UART that disables its receiver while transmitting.
+    pub fn without_echo(serial: S) -> Self {
+        UartBackend {
+            serial: serial,
+            echo: false,
+            config: Config::default(),
+            link: Link::default(),
+        }
+    }
+
+    /// Of `config`, only checksums, payload limit and retries matter here;
+    /// baud rate and framing are the UART's own.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        return self;
+    }
+
+    pub fn release(self) -> S {
+        return self.serial;
+    }
+
+    /// Sends `data`; fails with `Error::Busy` if the echo shows a collision.
+    pub fn write(&mut self, data: u8) -> Result<(), Error> {
+        io_err!(nb::block!(self.serial.write(data)))?;
+        if !self.echo {
+            return Ok(());
+        }
+
+        io_err!(nb::block!(self.serial.flush()))?;
+        if io_err!(nb::block!(self.serial.read()))? != data {
+            return Err(Error::Busy);
+        }
+        return Ok(());
+    }
+
+    pub fn read(&mut self) -> Result<u8, Error> {
+        return io_err!(nb::block!(self.serial.read()));
+    }
+}
+
+impl<S, T> ReadWrite<T> for UartBackend<S>
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    fn write(&mut self, data: u8, _delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return UartBackend::write(self, data);
+    }
+
+    fn read(&mut self, _delay: &mut impl PhaseDelay<T>) -> Result<u8, Error> {
+        return UartBackend::read(self);
+    }
+
+    fn config(&self) -> Config {
+        return self.config;
+    }
+}
+
+impl<S, T> PacketTransport<T> for UartBackend<S>
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    fn link(&mut self) -> &mut Link {
+        return &mut self.link;
+    }
+}
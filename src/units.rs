@@ -0,0 +1,47 @@
+//! Unit-tagged phase durations. The delay provider has to match the unit the
+//! wire was configured with, otherwise the call does not compile.
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Millis<T>(pub T);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Micros<T>(pub T);
+
+pub trait Unit: Copy {
+    fn as_micros(self) -> u32;
+}
+
+impl<T: Copy + Into<u32>> Unit for Millis<T> {
+    fn as_micros(self) -> u32 {
+        return self.0.into().saturating_mul(1000);
+    }
+}
+
+impl<T: Copy + Into<u32>> Unit for Micros<T> {
+    fn as_micros(self) -> u32 {
+        return self.0.into();
+    }
+}
+
+pub trait PhaseDelay<P> {
+    fn delay_phase(&mut self, phase: P);
+}
+
+impl<T, D> PhaseDelay<Millis<T>> for D
+where
+    D: DelayMs<T>,
+{
+    fn delay_phase(&mut self, phase: Millis<T>) {
+        self.delay_ms(phase.0);
+    }
+}
+
+impl<T, D> PhaseDelay<Micros<T>> for D
+where
+    D: DelayUs<T>,
+{
+    fn delay_phase(&mut self, phase: Micros<T>) {
+        self.delay_us(phase.0);
+    }
+}
@@ -0,0 +1,209 @@
+//! Receive windows of duty-cycled peers. A sleeping slave advertises when it
+//! listens; the master keeps frames for it queued until the window opens.
+//! All times are milliseconds of the local monotonic clock.
+//!
+//! ```text
+//! request:       | WINDOW_REQUEST |
+//! advertisement: | WINDOW_ADVERT | period | listen | next |
+//! ```
+//!
+//! The slave sends the advertisement on request, or on its own, e.g. right
+//! before it goes to sleep.
+use crate::units::PhaseDelay;
+use crate::{Error, ReadWrite};
+
+pub const WINDOW_LEN: usize = 6;
+pub const WINDOW_REQUEST: u8 = 0xa6;
+/// Starts an advertisement; the `WINDOW_LEN` bytes of `to_bytes` follow.
+pub const WINDOW_ADVERT: u8 = 0xa7;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReceiveWindow {
+    /// Time between the start of two windows.
+    pub period: u16,
+    /// How long the peer listens once a window opened.
+    pub listen: u16,
+    /// Time until the next window opens, counted from the advertisement.
+    pub next: u16,
+}
+
+impl ReceiveWindow {
+    pub fn to_bytes(&self) -> [u8; WINDOW_LEN] {
+        let mut bytes = [0u8; WINDOW_LEN];
+        bytes[0..2].copy_from_slice(&self.period.to_be_bytes());
+        bytes[2..4].copy_from_slice(&self.listen.to_be_bytes());
+        bytes[4..6].copy_from_slice(&self.next.to_be_bytes());
+        return bytes;
+    }
+
+    pub fn from_bytes(bytes: [u8; WINDOW_LEN]) -> Self {
+        ReceiveWindow {
+            period: u16::from_be_bytes([bytes[0], bytes[1]]),
+            listen: u16::from_be_bytes([bytes[2], bytes[3]]),
+            next: u16::from_be_bytes([bytes[4], bytes[5]]),
+        }
+    }
+
+    /// Anchors the advertisement to the local clock, `now` being the time it
+    /// was received.
+    pub fn anchor(self, now: u32) -> PeerWindow {
+        PeerWindow {
+            period: self.period as u32,
+            listen: self.listen as u32,
+            opens: now.wrapping_add(self.next as u32),
+        }
+    }
+}
+
+/// Slave side: tells the master when we listen next.
+pub fn advertise_window<T>(
+    wire: &mut impl ReadWrite<T>,
+    window: &ReceiveWindow,
+    delay: &mut impl PhaseDelay<T>,
+) -> Result<(), Error> {
+    wire.write(WINDOW_ADVERT, delay)?;
+    for byte in window.to_bytes() {
+        wire.write(byte, delay)?;
+    }
+    return Ok(());
+}
+
+/// Master side: reads an advertisement. `anchor` it as soon as this returns,
+/// its `next` counts from now.
+pub fn read_window<T>(
+    wire: &mut impl ReadWrite<T>,
+    delay: &mut impl PhaseDelay<T>,
+) -> Result<ReceiveWindow, Error> {
+    if wire.read(delay)? != WINDOW_ADVERT {
+        return Err(Error::Framing);
+    }
+    let mut bytes = [0u8; WINDOW_LEN];
+    wire.read_exact(&mut bytes, delay)?;
+    return Ok(ReceiveWindow::from_bytes(bytes));
+}
+
+/// Master side: asks a listening slave for its window, see `read_window`.
+pub fn fetch_window<T>(
+    wire: &mut impl ReadWrite<T>,
+    delay: &mut impl PhaseDelay<T>,
+) -> Result<ReceiveWindow, Error> {
+    wire.write(WINDOW_REQUEST, delay)?;
+    return read_window(wire, delay);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerWindow {
+    period: u32,
+    listen: u32,
+    opens: u32,
+}
+
+impl PeerWindow {
+    pub fn is_open(&self, now: u32) -> bool {
+        return self.until_open(now) == 0;
+    }
+
+    /// Milliseconds until the peer listens, 0 while a window is open.
+    pub fn until_open(&self, now: u32) -> u32 {
+        let since = now.wrapping_sub(self.opens);
+        if since > u32::MAX / 2 {
+            // The first window is still ahead of us.
+            return self.opens.wrapping_sub(now);
+        }
+        if self.period == 0 {
+            return 0;
+        }
+
+        let phase = since % self.period;
+        if phase < self.listen {
+            return 0;
+        }
+        return self.period - phase;
+    }
+}
+
+struct Queued<const M: usize> {
+    window: PeerWindow,
+    len: usize,
+    data: [u8; M],
+}
+
+/// Holds up to `N` frames of at most `M` bytes each.
+pub struct WindowScheduler<const N: usize, const M: usize> {
+    queue: [Option<Queued<M>>; N],
+}
+
+impl<const N: usize, const M: usize> WindowScheduler<N, M> {
+    pub const fn new() -> Self {
+        WindowScheduler {
+            queue: [const { None }; N],
+        }
+    }
+
+    pub fn enqueue(&mut self, window: PeerWindow, frame: &[u8]) -> Result<(), Error> {
+        if frame.len() > M {
+            return Err(Error::Framing);
+        }
+
+        let slot = match self.queue.iter_mut().find(|q| q.is_none()) {
+            Some(s) => s,
+            None => return Err(Error::Busy),
+        };
+
+        let mut data = [0u8; M];
+        data[..frame.len()].copy_from_slice(frame);
+        *slot = Some(Queued {
+            window: window,
+            len: frame.len(),
+            data: data,
+        });
+        return Ok(());
+    }
+
+    pub fn pending(&self) -> usize {
+        return self.queue.iter().filter(|q| q.is_some()).count();
+    }
+
+    /// Milliseconds until the earliest queued frame can go out.
+    pub fn next_due(&self, now: u32) -> Option<u32> {
+        return self
+            .queue
+            .iter()
+            .flatten()
+            .map(|q| q.window.until_open(now))
+            .min();
+    }
+
+    /// Sends every queued frame whose peer is listening at `now` and returns
+    /// how many went out. A failed frame stays queued.
+    pub fn dispatch<T>(
+        &mut self,
+        now: u32,
+        wire: &mut impl ReadWrite<T>,
+        delay: &mut impl PhaseDelay<T>,
+    ) -> Result<usize, Error> {
+        let mut sent = 0;
+
+        for slot in self.queue.iter_mut() {
+            let queued = match slot {
+                Some(q) if q.window.is_open(now) => q,
+                _ => continue,
+            };
+
+            for &byte in &queued.data[..queued.len] {
+                wire.write(byte, delay)?;
+            }
+
+            *slot = None;
+            sent += 1;
+        }
+
+        return Ok(sent);
+    }
+}
+
+impl<const N: usize, const M: usize> Default for WindowScheduler<N, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,39 @@
+//! Multi-byte words in a single frame: one start condition, then the bytes
+//! most significant first, instead of one frame per byte. `BitOrder` still
+//! applies per byte, so under `LsbFirst` each byte is reversed on its own,
+//! not the whole word. Receivers have to expect the same width; a frame of
+//! any other length is `Error::Desync`, with either encoding.
+use crate::observer::WireObserver;
+use crate::units::PhaseDelay;
+use crate::{Error, HalfDuplexWire};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+impl<F2, F1, I, O, T, V> HalfDuplexWire<F2, F1, I, O, T, V>
+where
+    F1: Fn(O) -> I,
+    F2: Fn(I) -> O,
+    I: InputPin,
+    O: OutputPin,
+    T: Copy,
+    V: WireObserver,
+{
+    pub fn write_u16(&mut self, value: u16, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return self.write_unit(&value.to_be_bytes(), delay);
+    }
+
+    pub fn read_u16(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u16, Error> {
+        let mut buf = [0u8; 2];
+        self.read_unit(&mut buf, &mut (), delay)?;
+        return Ok(u16::from_be_bytes(buf));
+    }
+
+    pub fn write_u32(&mut self, value: u32, delay: &mut impl PhaseDelay<T>) -> Result<(), Error> {
+        return self.write_unit(&value.to_be_bytes(), delay);
+    }
+
+    pub fn read_u32(&mut self, delay: &mut impl PhaseDelay<T>) -> Result<u32, Error> {
+        let mut buf = [0u8; 4];
+        self.read_unit(&mut buf, &mut (), delay)?;
+        return Ok(u32::from_be_bytes(buf));
+    }
+}
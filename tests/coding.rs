@@ -0,0 +1,161 @@
+//! Properties of the pin-free encoder and decoder, over every byte.
+use half_duplex_wire::coding::{encode_byte, encode_word, Decoder, Pulse, Segment};
+use half_duplex_wire::config::{BitOrder, Config, Encoding, Parity};
+use half_duplex_wire::Error;
+
+fn configs() -> Vec<Config> {
+    let mut configs = Vec::new();
+    for &encoding in &[Encoding::PulseWidth, Encoding::Manchester] {
+        for &parity in &[Parity::None, Parity::Even, Parity::Odd] {
+            for &bit_order in &[BitOrder::MsbFirst, BitOrder::LsbFirst] {
+                configs.push(Config {
+                    encoding,
+                    parity,
+                    bit_order,
+                    ..Config::default()
+                });
+            }
+        }
+    }
+    configs
+}
+
+/// Neighbours at the same level, as a receiver sees them.
+fn levels(segments: &[Segment]) -> Vec<(bool, u32)> {
+    let mut levels: Vec<(bool, u32)> = Vec::new();
+    for s in segments {
+        match levels.last_mut() {
+            Some(last) if last.0 == s.high => last.1 += s.phases as u32,
+            _ => levels.push((s.high, s.phases as u32)),
+        }
+    }
+    levels
+}
+
+/// Feeds a frame and the idle line after it, each level's duration scaled
+/// by `jitter`, which gets the level's index and returns percent.
+fn decode(
+    config: &Config,
+    segments: &[Segment],
+    jitter: impl Fn(usize) -> u32,
+) -> Result<Option<u8>, Error> {
+    let mut decoder = Decoder::new(config);
+    let mut levels = levels(segments);
+    // A trailing high runs into the idle line.
+    match levels.last_mut() {
+        Some(last) if last.0 => last.1 += 8,
+        _ => levels.push((true, 8)),
+    }
+    let (idle, frame) = levels.split_last().unwrap();
+    for (i, &(high, phases)) in frame.iter().enumerate() {
+        assert_eq!(decoder.feed(high, phases * 8 * jitter(i) / 100)?, None);
+    }
+    let word = decoder.feed(idle.0, idle.1 * 8)?;
+    Ok(word.map(|w| config.bit_order.apply(w as u8)))
+}
+
+#[test]
+fn every_byte_roundtrips() {
+    for config in configs() {
+        for byte in 0..=255u8 {
+            let segments: Vec<Segment> = encode_byte(&config, byte).collect();
+            assert_eq!(decode(&config, &segments, |_| 100), Ok(Some(byte)));
+        }
+    }
+}
+
+#[test]
+fn roundtrip_survives_jitter() {
+    let config = Config::default();
+    let mut seed = 0x2545_f491u32;
+    for byte in 0..=255u8 {
+        let segments: Vec<Segment> = encode_byte(&config, byte).collect();
+        let jitter: Vec<u32> = segments
+            .iter()
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                // 80% to 115%
+                80 + (seed >> 16) % 36
+            })
+            .collect();
+        assert_eq!(decode(&config, &segments, |i| jitter[i]), Ok(Some(byte)));
+    }
+}
+
+#[test]
+fn pulse_width_frame_has_fixed_length() {
+    let config = Config::default();
+    for byte in 0..=255u8 {
+        let phases: u32 = encode_byte(&config, byte).map(|s| s.phases as u32).sum();
+        assert_eq!(phases, config.start_phases as u32 + 8 * 8);
+    }
+}
+
+#[test]
+fn segments_mark_where_bits_start() {
+    for config in configs() {
+        let bits: Vec<bool> = encode_word(&config, 0x5a, 8)
+            .filter_map(|s| s.bit)
+            .collect();
+        let mut expected: Vec<bool> = (0..8).rev().map(|i| 0x5a >> i & 1 != 0).collect();
+        expected.extend(config.parity.bit(0x5a));
+        assert_eq!(bits, expected);
+    }
+}
+
+#[test]
+fn manchester_halves_need_a_transition() {
+    let config = Config {
+        encoding: Encoding::Manchester,
+        ..Config::default()
+    };
+    let mut decoder = Decoder::new(&config);
+    decoder.begin();
+    // The sync bit, then a one: neither comes out as data but the one.
+    assert_eq!(decoder.push_half(true), Ok(None));
+    assert_eq!(decoder.push_half(false), Ok(None));
+    assert_eq!(decoder.push_half(false), Ok(None));
+    assert_eq!(decoder.push_half(true), Ok(Some(true)));
+    assert_eq!(decoder.push_half(true), Ok(None));
+    assert_eq!(decoder.push_half(true), Err(Error::Framing));
+    assert!(!decoder.in_frame());
+}
+
+#[test]
+fn pushed_frames_check_their_length() {
+    let config = Config {
+        parity: Parity::Even,
+        ..Config::default()
+    };
+    let mut decoder = Decoder::with_bits(&config, 2);
+
+    decoder.begin();
+    assert_eq!(decoder.push(Pulse::Stop), Err(Error::BreakReceived));
+
+    decoder.begin();
+    for bit in [true, false, true] {
+        assert_eq!(decoder.push(Pulse::Bit(bit)), Ok(None));
+    }
+    assert!(decoder.is_complete());
+    assert_eq!(decoder.push(Pulse::Stop), Ok(Some(0b10)));
+
+    decoder.begin();
+    for bit in [true, false, true, true] {
+        decoder.push(Pulse::Bit(bit)).unwrap();
+    }
+    assert_eq!(decoder.push(Pulse::Stop), Err(Error::Desync));
+
+    decoder.begin();
+    for bit in [true, true, true] {
+        decoder.push(Pulse::Bit(bit)).unwrap();
+    }
+    assert_eq!(decoder.finish(), Err(Error::Parity));
+}
+
+#[test]
+fn decoder_rejects_out_of_band_pulse() {
+    let config = Config::default();
+    let mut decoder = Decoder::new(&config);
+    assert_eq!(decoder.feed(false, 32), Ok(None));
+    assert_eq!(decoder.feed(true, 4), Err(Error::InvalidPulse));
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the integration tests. Every test crate uses a
+//! different subset of them.
+#![allow(dead_code)]
+use half_duplex_wire::crc::{Crc16, CrcEngine};
+use half_duplex_wire::testing::Line;
+
+/// Phases of quiet line that leave our side time to answer.
+pub const QUIET: u32 = 600;
+
+/// A packet as `send_packet` puts it on the wire, checksummed by `Crc16`.
+pub fn packet(kind: u8, seq: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![kind, seq, payload.len() as u8];
+    bytes.extend_from_slice(payload);
+    let mut crc = Crc16::new();
+    crc.update(&bytes);
+    bytes.extend_from_slice(&(crc.finish() as u16).to_be_bytes());
+    bytes
+}
+
+/// Everything our side wrote to `line`, as bytes.
+pub fn written(line: &Line) -> Vec<u8> {
+    let mut bits = [false; 512];
+    let n = line.written_bits(&mut bits);
+    bits[..n]
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, &b| acc << 1 | b as u8))
+        .collect()
+}
+
+/// Scripts `bytes` from the peer, one frame each.
+pub fn script(line: &Line, bytes: &[u8]) {
+    for &byte in bytes {
+        line.script_byte(byte);
+    }
+}
+
+/// Scripts `bytes`, then `QUIET` phases for our reply.
+pub fn script_then_quiet(line: &Line, bytes: &[u8]) {
+    script(line, bytes);
+    line.script(false, QUIET);
+}
@@ -0,0 +1,1214 @@
+//! Transfers against the simulated line of the `testing` feature.
+mod common;
+
+use common::{packet, script, script_then_quiet, written};
+use embedded_hal::blocking::delay::DelayUs;
+use half_duplex_wire::buffer::RxBuffer;
+use half_duplex_wire::calibrate::TRAINING;
+use half_duplex_wire::capture::{Edge, EdgeBuffer};
+use half_duplex_wire::config::{
+    BitOrder, Config, ConfigError, Encoding, Parity, Sampling, Timing, MIN_TIMEOUT_TICKS,
+};
+use half_duplex_wire::control::ENTER_LOW_POWER;
+use half_duplex_wire::crc::Crc16;
+use half_duplex_wire::endian::WireValue;
+use half_duplex_wire::event::{EventQueue, WireEvent};
+use half_duplex_wire::handshake::{CAP_CRC, CAP_MANCHESTER, HELLO};
+use half_duplex_wire::observer::{Direction, WireObserver};
+use half_duplex_wire::packet::{KIND_CONTROL, KIND_DATA, KIND_NAK};
+use half_duplex_wire::queue::TxQueue;
+use half_duplex_wire::testing::{mock_wire, Line, LineInput, LineOutput, MockWire};
+use half_duplex_wire::units::Micros;
+use half_duplex_wire::window::{
+    advertise_window, fetch_window, ReceiveWindow, WINDOW_ADVERT, WINDOW_REQUEST,
+};
+use half_duplex_wire::{EdgeDetector, Error, HalfDuplexWire};
+
+fn bits_of(word: u32, count: usize) -> Vec<bool> {
+    (0..count).rev().map(|i| word >> i & 1 != 0).collect()
+}
+
+#[test]
+fn write_sends_msb_first() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    wire.write(0xa5, &mut line.delay()).unwrap();
+
+    let mut bits = [false; 16];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(0xa5, 8)[..]);
+}
+
+#[test]
+fn lsb_first_reverses_both_directions() {
+    let line = Line::new(10);
+    let config = Config {
+        bit_order: BitOrder::LsbFirst,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    wire.write(0x01, &mut line.delay()).unwrap();
+    let mut bits = [false; 16];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(0x80, 8)[..]);
+
+    line.script_byte(0xc0);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0x03));
+}
+
+#[test]
+fn owned_delay_needs_no_delay_argument() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_owned_delay(line.delay());
+
+    wire.write(0xa5).unwrap();
+    line.script_byte(0x3c);
+    assert_eq!(wire.read(), Ok(0x3c));
+
+    let mut bits = [false; 16];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(0xa5, 8)[..]);
+}
+
+#[test]
+fn write_u16_is_one_frame() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    wire.write_u16(0xbeef, &mut line.delay()).unwrap();
+
+    let mut bits = [false; 32];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(0xbeef, 16)[..]);
+    assert_eq!(wire.stats().frames_sent, 1);
+}
+
+#[test]
+fn lsb_first_reverses_each_byte_of_a_word() {
+    let line = Line::new(10);
+    let config = Config {
+        bit_order: BitOrder::LsbFirst,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    wire.write_u32(0x0102_0304, &mut line.delay()).unwrap();
+    let mut bits = [false; 64];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(0x8040_c020, 32)[..]);
+
+    line.script_frame(0x8040_c020, 32, 4);
+    assert_eq!(wire.read_u32(&mut line.delay()), Ok(0x0102_0304));
+}
+
+/// Sends and receives `value` both ways round; `le` is its little-endian
+/// encoding.
+fn check_byte_orders<U: WireValue + PartialEq + core::fmt::Debug>(value: U, le: &[u8]) {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    wire.put_le(value, &mut line.delay()).unwrap();
+    assert_eq!(written(&line), le);
+    script(&line, le);
+    assert_eq!(wire.get_le::<U>(&mut line.delay()), Ok(value));
+
+    let be: Vec<u8> = le.iter().rev().copied().collect();
+    line.clear_written();
+    wire.put_be(value, &mut line.delay()).unwrap();
+    assert_eq!(written(&line), be);
+    script(&line, &be);
+    assert_eq!(wire.get_be::<U>(&mut line.delay()), Ok(value));
+}
+
+#[test]
+fn values_roundtrip_in_both_byte_orders() {
+    check_byte_orders(0x12u8, &[0x12]);
+    check_byte_orders(0x1234u16, &[0x34, 0x12]);
+    check_byte_orders(-2i32, &[0xfe, 0xff, 0xff, 0xff]);
+    check_byte_orders(1.0f32, &[0x00, 0x00, 0x80, 0x3f]);
+    check_byte_orders(0x0102_0304_0506_0708u64, &[8, 7, 6, 5, 4, 3, 2, 1]);
+    check_byte_orders(-1.5f64, &[0, 0, 0, 0, 0, 0, 0xf8, 0xbf]);
+}
+
+#[test]
+fn parity_bit_follows_data() {
+    let line = Line::new(10);
+    let config = Config {
+        parity: Parity::Even,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    wire.write(0x01, &mut line.delay()).unwrap();
+
+    let mut bits = [false; 16];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(n, 9);
+    assert!(bits[8]);
+}
+
+#[test]
+fn read_decodes_scripted_byte() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    line.script_byte(0x3c);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0x3c));
+
+    line.script_byte(0xff);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0xff));
+    assert_eq!(wire.stats().frames_received, 2);
+}
+
+#[test]
+fn read_rejects_wrong_width() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    line.script_frame(0x1234, 16, 4);
+    assert_eq!(wire.read(&mut line.delay()), Err(Error::Desync));
+
+    line.script_frame(0x1234, 16, 4);
+    assert_eq!(wire.read_u16(&mut line.delay()), Ok(0x1234));
+}
+
+#[test]
+fn read_exact_fills_the_buffer() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    script(&line, &[1, 2, 3, 4]);
+    let mut buf = [0u8; 3];
+    wire.read_exact(&mut buf, &mut line.delay()).unwrap();
+    assert_eq!(buf, [1, 2, 3]);
+    assert_eq!(wire.read(&mut line.delay()), Ok(4));
+}
+
+#[test]
+fn read_until_idle_stops_when_full() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    script(&line, &[1, 2, 3, 4]);
+    let mut buf = [0u8; 3];
+    assert_eq!(wire.read_until_idle(&mut buf, &mut line.delay()), Ok(3));
+    assert_eq!(buf, [1, 2, 3]);
+}
+
+#[test]
+fn read_until_idle_stops_at_an_early_idle() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    script(&line, &[1, 2]);
+    let mut buf = [0u8; 8];
+    assert_eq!(wire.read_until_idle(&mut buf, &mut line.delay()), Ok(2));
+    assert_eq!(&buf[..2], &[1, 2]);
+}
+
+#[test]
+fn transfer_in_place_overwrites_with_the_reply() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    // The reply starts once the three request bytes are out.
+    line.script(false, 250);
+    script(&line, &[7, 8, 9]);
+    let mut buf = [1, 2, 3];
+    wire.transfer_in_place(&mut buf, &mut line.delay()).unwrap();
+
+    assert_eq!(written(&line), [1, 2, 3]);
+    assert_eq!(buf, [7, 8, 9]);
+}
+
+#[test]
+fn read_times_out_on_idle_line() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    assert_eq!(
+        wire.read_timeout(20, &mut line.delay()),
+        Err(Error::Timeout)
+    );
+    assert!(line.now() >= 200);
+}
+
+#[test]
+fn write_refuses_busy_line() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    line.script(true, 100);
+    assert_eq!(wire.write(0x00, &mut line.delay()), Err(Error::Busy));
+
+    let mut bits = [false; 8];
+    assert_eq!(line.written_bits(&mut bits), 0);
+}
+
+#[test]
+fn csma_write_backs_off_until_line_is_free() {
+    let line = Line::new(10);
+    let config = Config {
+        csma: Some(4),
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+    wire.seed_backoff(0x1234);
+
+    line.script(true, 100);
+    assert_eq!(wire.write(0xc3, &mut line.delay()), Ok(()));
+    assert!(wire.stats().collisions >= 1);
+
+    let mut bits = [false; 16];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(0xc3, 8)[..]);
+}
+
+#[test]
+fn transfer_reads_reply_after_command() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    // The peer answers once the command is through.
+    line.script(false, 120);
+    line.script_byte(0x99);
+    line.script_byte(0x66);
+
+    let mut resp = [0u8; 2];
+    assert_eq!(wire.transfer(&[0x10], &mut resp, &mut line.delay()), Ok(()));
+    assert_eq!(resp, [0x99, 0x66]);
+}
+
+#[test]
+fn transfer_without_reply_is_no_response() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    let mut resp = [0u8; 1];
+    assert_eq!(
+        wire.transfer(&[0x10], &mut resp, &mut line.delay()),
+        Err(Error::NoResponse)
+    );
+}
+
+#[test]
+fn recv_frame_checks_length() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    for &byte in &[3, 0x10, 0x20, 0x30, 1, 0x40] {
+        line.script_byte(byte);
+    }
+
+    let mut buf = [0u8; 2];
+    assert_eq!(
+        wire.recv_frame(&mut buf, &mut line.delay()),
+        Err(Error::FrameTooLarge)
+    );
+    assert_eq!(wire.recv_frame(&mut buf, &mut line.delay()), Ok(1));
+    assert_eq!(buf[0], 0x40);
+}
+
+#[test]
+fn read_decodes_manchester() {
+    let line = Line::new(10);
+    let config = Config {
+        encoding: Encoding::Manchester,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    for &byte in &[0x00u8, 0xff, 0x5a] {
+        line.script_manchester(byte as u32, 8, 4);
+        assert_eq!(wire.read(&mut line.delay()), Ok(byte));
+    }
+}
+
+#[test]
+fn recover_resumes_after_desync() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    line.script_frame(0x1234, 16, 4);
+    assert_eq!(wire.read(&mut line.delay()), Err(Error::Desync));
+
+    wire.recover(&mut line.delay()).unwrap();
+    line.script_byte(0x3c);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0x3c));
+}
+
+#[test]
+fn read_rejects_wrong_width_in_manchester() {
+    let line = Line::new(10);
+    let config = Config {
+        encoding: Encoding::Manchester,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    line.script_manchester(0x1234, 16, 4);
+    assert_eq!(wire.read(&mut line.delay()), Err(Error::Desync));
+
+    line.script_manchester(0x1234, 16, 4);
+    assert_eq!(wire.read_u16(&mut line.delay()), Ok(0x1234));
+}
+
+#[test]
+fn read_reports_truncated_frame() {
+    let line = Line::new(10);
+    let config = Config {
+        bit_timeout: Some(40),
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    line.script(true, 4);
+    for _ in 0..3 {
+        line.script(false, 4);
+        line.script(true, 4);
+    }
+    line.script(true, 1000);
+
+    let result = wire.read(&mut line.delay());
+    assert_eq!(result, Err(Error::Truncated { bits_received: 3 }));
+    assert!(line.now() < 1000 * 10);
+}
+
+#[test]
+fn tx_queue_waits_out_busy_line() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let mut queue = TxQueue::<8>::new();
+
+    queue.enqueue_frame(&[0x12, 0x34]).unwrap();
+    assert_eq!(queue.enqueue_frame(&[0; 7]), Err(Error::Busy));
+
+    line.script(true, 50);
+    assert_eq!(queue.flush(&mut wire, &mut line.delay()), Ok(2));
+    assert!(queue.is_empty());
+
+    let mut bits = [false; 16];
+    assert_eq!(line.written_bits(&mut bits), 16);
+}
+
+#[derive(Default)]
+struct BitLog {
+    tx: Vec<bool>,
+    rx: Vec<bool>,
+}
+
+impl WireObserver for BitLog {
+    fn on_bit(&mut self, dir: Direction, value: bool) {
+        match dir {
+            Direction::Tx => self.tx.push(value),
+            Direction::Rx => self.rx.push(value),
+        }
+    }
+}
+
+#[test]
+fn observer_sees_every_bit() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_observer(BitLog::default());
+
+    wire.write(0x81, &mut line.delay()).unwrap();
+    line.script_byte(0x3c);
+    wire.read(&mut line.delay()).unwrap();
+
+    assert_eq!(wire.observer().tx, bits_of(0x81, 8));
+    assert_eq!(wire.observer().rx, bits_of(0x3c, 8));
+}
+
+#[test]
+fn rx_buffer_collects_until_full() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_observer(RxBuffer::<4>::new());
+
+    for byte in 1..=5 {
+        line.script_byte(byte);
+        wire.read(&mut line.delay()).unwrap();
+    }
+
+    let rx = wire.observer_mut();
+    assert_eq!(rx.available(), 4);
+    assert_eq!(rx.overflows(), 1);
+    let mut buf = [0u8; 8];
+    assert_eq!(rx.pop_into(&mut buf), 4);
+    assert_eq!(&buf[..4], &[1, 2, 3, 4]);
+}
+
+#[test]
+fn event_queue_keeps_order_until_full() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_observer(EventQueue::<2>::new());
+
+    wire.write(0x5a, &mut line.delay()).unwrap();
+    line.script_byte(0x3c);
+    wire.read(&mut line.delay()).unwrap();
+    assert_eq!(wire.read_timeout(4, &mut line.delay()), Err(Error::Timeout));
+
+    let events = wire.observer_mut();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events.dropped(), 1);
+    assert_eq!(events.pop(), Some(WireEvent::FrameSent { len: 1 }));
+    assert_eq!(events.pop(), Some(WireEvent::FrameReceived { len: 1 }));
+    assert_eq!(events.pop(), None);
+
+    // Room again once drained.
+    events.push(WireEvent::LinkLost);
+    assert_eq!(events.pop(), Some(WireEvent::LinkLost));
+}
+
+#[test]
+fn read_addressed_skips_other_devices() {
+    let line = Line::new(10);
+    let config = Config {
+        address: Some(0x21),
+        groups: 0b10,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    wire.write_to(0x22, 0x5a, &mut line.delay()).unwrap();
+    assert_eq!(written(&line), [0x22, 0x5a]);
+
+    script(&line, &[0x22, 0x01, 0x21, 0x02, 0xf0, 0x03, 0xf1, 0x04]);
+    assert_eq!(wire.read_addressed(&mut line.delay()), Ok((0x21, 0x02)));
+    assert_eq!(wire.read_addressed(&mut line.delay()), Ok((0xf1, 0x04)));
+}
+
+#[test]
+fn hello_settles_on_common_capabilities() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    for &byte in &[HELLO, 0x01, 0x00, 64] {
+        line.script_byte(byte);
+    }
+    let common = wire.hello_reply(&mut line.delay()).unwrap();
+
+    assert_eq!(common.flags & CAP_CRC, 0);
+    assert!(!wire.config().crc);
+    assert_eq!(wire.config().max_payload, 64);
+    assert_eq!(wire.peer_capabilities().map(|c| c.max_frame), Some(64));
+
+    let mut bits = [false; 64];
+    assert_eq!(line.written_bits(&mut bits), 32);
+}
+
+#[test]
+fn hello_negotiates_line_coding() {
+    let line = Line::new(10);
+    let manchester = Config {
+        encoding: Encoding::Manchester,
+        ..Config::default()
+    };
+
+    // Both ask for Manchester; the hello itself still goes pulse-width.
+    let mut wire = mock_wire(&line).with_config(manchester);
+    let own = wire.capabilities().to_bytes();
+    for &byte in &[HELLO, 0x01, CAP_MANCHESTER, 64] {
+        line.script_byte(byte);
+    }
+    let common = wire.hello_reply(&mut line.delay()).unwrap();
+    assert_ne!(common.flags & CAP_MANCHESTER, 0);
+    assert_eq!(wire.config().encoding, Encoding::Manchester);
+    let mut bits = [false; 64];
+    let n = line.written_bits(&mut bits);
+    let expected: Vec<bool> = own.iter().flat_map(|&b| bits_of(b as u32, 8)).collect();
+    assert_eq!(&bits[..n], &expected[..]);
+
+    // A peer that only speaks pulse width.
+    let mut wire = mock_wire(&line).with_config(manchester);
+    for &byte in &[HELLO, 0x01, 0x00, 64] {
+        line.script_byte(byte);
+    }
+    let common = wire.hello_reply(&mut line.delay()).unwrap();
+    assert_eq!(common.flags & CAP_MANCHESTER, 0);
+    assert_eq!(wire.config().encoding, Encoding::PulseWidth);
+}
+
+#[test]
+fn hello_refuses_other_protocol_version() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    for &byte in &[HELLO, 0x02, CAP_CRC, 255] {
+        line.script_byte(byte);
+    }
+    assert_eq!(
+        wire.hello_reply(&mut line.delay()),
+        Err(Error::IncompatiblePeer)
+    );
+    assert!(wire.config().crc);
+}
+
+#[test]
+fn recv_large_joins_chunks() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    script_then_quiet(&line, &packet(KIND_DATA, 0, &[0, 5, 0, 0, 1, 2, 3]));
+    // A retransmission whose ack got lost.
+    script_then_quiet(&line, &packet(KIND_DATA, 0, &[0, 5, 0, 0, 1, 2, 3]));
+    script_then_quiet(&line, &packet(KIND_DATA, 1, &[0, 5, 0, 3, 4, 5]));
+
+    let mut buf = [0u8; 8];
+    let mut progress = Vec::new();
+    let len = wire.recv_large(
+        &mut buf,
+        &mut Crc16::new(),
+        |done, total| progress.push((done, total)),
+        &mut line.delay(),
+    );
+    assert_eq!(len, Ok(5));
+    assert_eq!(&buf[..5], &[1, 2, 3, 4, 5]);
+    assert_eq!(progress, [(3, 5), (5, 5)]);
+}
+
+#[test]
+fn recv_large_refuses_oversized_transfer() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    script_then_quiet(&line, &packet(KIND_DATA, 0, &[0, 16, 0, 0, 1, 2, 3]));
+
+    let mut buf = [0u8; 8];
+    let mut progress = 0;
+    let len = wire.recv_large(
+        &mut buf,
+        &mut Crc16::new(),
+        |done, _| progress = done,
+        &mut line.delay(),
+    );
+    assert_eq!(len, Err(Error::FrameTooLarge));
+    assert_eq!(written(&line), packet(KIND_NAK, 0, &[]));
+    assert_eq!(progress, 0);
+}
+
+#[test]
+fn messages_roundtrip() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let msg = (7u8, 0x1234u16, true);
+    let mut buf = [0u8; 16];
+
+    wire.send_msg(&msg, &mut buf, &mut line.delay()).unwrap();
+    let sent = written(&line);
+    assert_eq!(sent.last(), Some(&0));
+
+    script(&line, &sent);
+    assert_eq!(wire.recv_msg(&mut buf, &mut line.delay()), Ok(msg));
+}
+
+#[test]
+fn message_too_large_for_the_buffer() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let msg = (7u8, 0x1234u16, true);
+    let mut buf = [0u8; 2];
+
+    assert_eq!(
+        wire.send_msg(&msg, &mut buf, &mut line.delay()),
+        Err(Error::Codec)
+    );
+    assert_eq!(written(&line), []);
+
+    // Four bytes up to the closing zero, two more than fit.
+    script(&line, &[0x03, 0x07, 0x01, 0x00]);
+    assert_eq!(
+        wire.recv_msg::<(u8, u16, bool)>(&mut buf, &mut line.delay()),
+        Err(Error::Framing)
+    );
+}
+
+#[test]
+fn send_large_gives_up_without_acks() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_config(Config {
+        retries: 0,
+        ..Config::default()
+    });
+
+    let mut progress = 0;
+    assert_eq!(
+        wire.send_large(
+            &[0u8; 16],
+            8,
+            &mut Crc16::new(),
+            |done, _| progress = done,
+            &mut line.delay()
+        ),
+        Err(Error::NoResponse)
+    );
+    assert_eq!(progress, 0);
+}
+
+/// A wire timing pulses in fifths of a 100 µs phase, on a line scripted in
+/// 20 µs steps.
+fn measuring_wire(line: &Line) -> MockWire<'_> {
+    let config = Config {
+        sampling: Sampling::Measured,
+        timing: Timing { ticks_per_phase: 5 },
+        ..Config::default()
+    };
+    HalfDuplexWire::new(
+        line.input(),
+        LineInput::into_output as fn(_) -> _,
+        LineOutput::into_input as fn(_) -> _,
+        Micros(20u32),
+    )
+    .with_config(config)
+}
+
+/// Scripts `byte` from a peer whose phases are `scale` tenths of ours.
+fn script_scaled(line: &Line, byte: u8, scale: u32) {
+    let steps = |phases: u32| phases * 5 * scale / 10;
+    line.script(true, steps(4));
+    for i in (0..8).rev() {
+        let high = if byte >> i & 1 != 0 { 4 } else { 2 };
+        line.script(false, steps(high));
+        line.script(true, steps(8 - high));
+    }
+    line.script(false, steps(8));
+}
+
+#[test]
+fn low_power_round_trip() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let mut crc = Crc16::new();
+
+    wire.enter_low_power(&mut crc, &mut line.delay()).unwrap();
+    assert!(wire.is_low_power());
+    assert!(!line.is_low());
+    assert_eq!(written(&line), packet(KIND_CONTROL, 0, &[ENTER_LOW_POWER]));
+
+    // `Config::low_power_timing` makes phases eight times as long.
+    script_scaled(&line, 0x3c, 16);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0x3c));
+
+    wire.exit_low_power(&mut crc, &mut line.delay()).unwrap();
+    assert!(!wire.is_low_power());
+    assert!(!line.is_low());
+
+    line.clear_written();
+    wire.write(0x5a, &mut line.delay()).unwrap();
+    assert_eq!(written(&line), [0x5a]);
+    line.script_byte(0x42);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0x42));
+}
+
+#[test]
+fn measured_sampling_tolerates_slow_and_fast_peers() {
+    let line = Line::new(20);
+    let mut wire = measuring_wire(&line);
+
+    for &(byte, scale) in &[(0xa5u8, 10), (0x3c, 12), (0x81, 8)] {
+        script_scaled(&line, byte, scale);
+        assert_eq!(wire.read(&mut line.delay()), Ok(byte));
+    }
+}
+
+#[test]
+fn measured_sampling_rejects_short_pulse() {
+    let line = Line::new(20);
+    let mut wire = measuring_wire(&line);
+
+    line.script(true, 20);
+    line.script(false, 4);
+    line.script(true, 36);
+    line.script(false, 40);
+    assert_eq!(wire.read(&mut line.delay()), Err(Error::InvalidPulse));
+}
+
+#[test]
+fn resume_continues_with_suspended_state() {
+    let line = Line::new(10);
+    let config = Config {
+        parity: Parity::Odd,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+    wire.write(0x0f, &mut line.delay()).unwrap();
+
+    let (pin, state) = wire.suspend().unwrap();
+    let mut wire: MockWire = HalfDuplexWire::resume(
+        pin,
+        LineInput::into_output,
+        LineOutput::into_input,
+        Micros(10),
+        state,
+    );
+
+    assert_eq!(wire.config().parity, Parity::Odd);
+    assert_eq!(wire.stats().frames_sent, 1);
+    line.script_frame(0x0f << 1 | 1, 9, 4);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0x0f));
+}
+
+#[test]
+fn recv_packet_skips_oversize_packet() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_config(Config {
+        max_payload: 4,
+        ..Config::default()
+    });
+
+    script_then_quiet(&line, &packet(KIND_DATA, 0, &[9; 6]));
+    script_then_quiet(&line, &packet(KIND_DATA, 0, &[1, 2, 3]));
+
+    let mut buf = [0u8; 8];
+    let mut crc = Crc16::new();
+    assert_eq!(
+        wire.recv_packet(&mut buf, &mut crc, &mut line.delay())
+            .map(|p| p.seq),
+        Err(Error::Framing)
+    );
+    let packet = wire
+        .recv_packet(&mut buf, &mut crc, &mut line.delay())
+        .unwrap();
+    assert_eq!(packet.payload, &[1, 2, 3]);
+}
+
+#[test]
+fn failed_build_returns_the_builder() {
+    let line = Line::new(10);
+    let builder = HalfDuplexWire::builder(
+        line.input(),
+        LineInput::into_output as fn(_) -> _,
+        LineOutput::into_input as fn(_) -> _,
+        Micros(10u32),
+    )
+    .config(Config {
+        start_phases: 1,
+        ..Config::default()
+    });
+
+    let (err, builder) = builder.build().err().unwrap();
+    assert_eq!(err, ConfigError::StartTooShort);
+    let mut wire = builder.config(Config::default()).build().ok().unwrap();
+    wire.write(0x5a, &mut line.delay()).unwrap();
+
+    let mut bits = [false; 16];
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(0x5a, 8)[..]);
+}
+
+/// Steps `poll` once per phase until it reports something.
+fn poll_event(wire: &mut MockWire, line: &Line) -> WireEvent {
+    for _ in 0..1000 {
+        // Reading the pin takes the other microsecond.
+        line.advance(9);
+        if let Some(event) = wire.poll(line.now()) {
+            return event;
+        }
+    }
+    panic!("no event");
+}
+
+#[test]
+fn polled_write_matches_blocking_write() {
+    let line = Line::new(10);
+    let config = Config {
+        parity: Parity::Odd,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    wire.write(0x5a, &mut line.delay()).unwrap();
+    let mut blocking = [false; 16];
+    let n = line.written_bits(&mut blocking);
+    line.clear_written();
+
+    // A polled write does not read the pin, so every poll takes a phase.
+    wire.start_write(0x5a).unwrap();
+    let event = (0..1000).find_map(|_| {
+        line.advance(10);
+        wire.poll(line.now())
+    });
+    assert_eq!(event, Some(WireEvent::FrameSent { len: 1 }));
+    let mut polled = [false; 16];
+    assert_eq!(line.written_bits(&mut polled), n);
+    assert_eq!(polled, blocking);
+}
+
+#[test]
+fn poll_saturates_a_long_phase() {
+    let line = Line::new(10);
+    let config = Config {
+        timing: Timing { ticks_per_phase: 8 },
+        ..Config::default()
+    };
+    let mut wire = HalfDuplexWire::new(
+        line.input(),
+        LineInput::into_output as fn(_) -> _,
+        LineOutput::into_input as fn(_) -> _,
+        Micros(u32::MAX / 4),
+    )
+    .with_config(config);
+
+    wire.start_write(0x5a).unwrap();
+    assert_eq!(wire.poll(0), None);
+    // The next phase is still far away, not in the past.
+    assert_eq!(wire.poll(i32::MAX as u32 - 1), None);
+    assert_eq!(line.written_bits(&mut [false; 16]), 0);
+}
+
+#[test]
+fn bits_after_parity_are_desync() {
+    let line = Line::new(10);
+    let config = Config {
+        parity: Parity::Even,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line).with_config(config);
+
+    line.script_frame(0x7f << 3 | 0b111, 11, 4);
+    assert_eq!(wire.read(&mut line.delay()), Err(Error::Desync));
+
+    line.script_frame(0x7f << 3 | 0b111, 11, 4);
+    assert_eq!(
+        poll_event(&mut wire, &line),
+        WireEvent::Error(Error::Desync)
+    );
+
+    line.script_frame(0x7f << 1 | 1, 9, 4);
+    assert_eq!(
+        poll_event(&mut wire, &line),
+        WireEvent::FrameReceived { len: 1 }
+    );
+    assert_eq!(wire.take_received(), Some(0x7f));
+}
+
+#[test]
+fn bit_timeout_needs_fine_timing() {
+    let config = Config {
+        bit_timeout: Some(16),
+        ..Config::default()
+    };
+    assert_eq!(config.validate(), Err(ConfigError::CoarseTiming));
+
+    let config = Config {
+        timing: Timing {
+            ticks_per_phase: MIN_TIMEOUT_TICKS,
+        },
+        ..config
+    };
+    assert_eq!(config.validate(), Ok(()));
+}
+
+/// A wire whose delay ticks `tick_us`; `config.timing` makes up the phase.
+fn ticked_wire(line: &Line, tick_us: u32, config: Config) -> MockWire<'_> {
+    HalfDuplexWire::new(
+        line.input(),
+        LineInput::into_output as fn(_) -> _,
+        LineOutput::into_input as fn(_) -> _,
+        Micros(tick_us),
+    )
+    .with_config(config)
+}
+
+#[test]
+fn bit_timeout_roundtrips_every_byte() {
+    let line = Line::new(40);
+    let config = Config {
+        bit_timeout: Some(16),
+        timing: Timing {
+            ticks_per_phase: MIN_TIMEOUT_TICKS,
+        },
+        ..Config::default()
+    };
+    let mut wire = ticked_wire(&line, 10, config);
+
+    for byte in 0..=255u8 {
+        line.script_byte(byte);
+        assert_eq!(wire.read(&mut line.delay()), Ok(byte));
+    }
+}
+
+/// Delays that oversleep every tick by `lag` µs.
+struct LaggingDelay<'a> {
+    line: &'a Line,
+    lag: u32,
+}
+
+impl DelayUs<u32> for LaggingDelay<'_> {
+    fn delay_us(&mut self, us: u32) {
+        self.line.advance(us + self.lag);
+    }
+}
+
+#[test]
+fn double_sampling_flags_late_samples() {
+    let line = Line::new(40);
+    let config = Config {
+        sampling: Sampling::Double,
+        timing: Timing { ticks_per_phase: 4 },
+        ..Config::default()
+    };
+    assert_eq!(
+        Config {
+            timing: Timing::default(),
+            ..config
+        }
+        .validate(),
+        Err(ConfigError::CoarseTiming)
+    );
+    let mut wire = ticked_wire(&line, 10, config);
+
+    for byte in [0x00, 0x5a, 0xff] {
+        line.script_byte(byte);
+        assert_eq!(wire.read(&mut line.delay()), Ok(byte));
+    }
+
+    // The late sample lands behind a one pulse, the early one inside it.
+    line.script_byte(0xff);
+    let mut delay = LaggingDelay {
+        line: &line,
+        lag: 3,
+    };
+    assert_eq!(wire.read(&mut delay), Err(Error::Marginal));
+}
+
+/// Scripts 0xff in 5 µs steps of a 40 µs phase, each one pulse
+/// with a 10 µs dip right at the decision point.
+fn script_glitchy_ones(line: &Line) {
+    line.script(true, 32);
+    for _ in 0..8 {
+        line.script(false, 23);
+        line.script(true, 2);
+        line.script(false, 7);
+        line.script(true, 32);
+    }
+    line.script(false, 64);
+}
+
+#[test]
+fn majority_sampling_spreads_reads_over_ticks() {
+    let line = Line::new(5);
+    let config = Config {
+        sampling: Sampling::Majority(3),
+        timing: Timing { ticks_per_phase: 4 },
+        ..Config::default()
+    };
+    let coarse = Config {
+        timing: Timing { ticks_per_phase: 2 },
+        ..config
+    };
+    assert_eq!(coarse.validate(), Err(ConfigError::CoarseTiming));
+
+    let mut wire = ticked_wire(&line, 10, config);
+    script_glitchy_ones(&line);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0xff));
+
+    // A single read hits every dip.
+    let mut wire = ticked_wire(
+        &line,
+        10,
+        Config {
+            sampling: Sampling::Single,
+            ..config
+        },
+    );
+    script_glitchy_ones(&line);
+    assert_eq!(wire.read(&mut line.delay()), Ok(0x00));
+}
+
+#[test]
+fn edge_filter_needs_depth_samples() {
+    // Every read ends a 1 µs phase, so a level of n phases is read n - 1
+    // times.
+    let line = Line::new(1);
+    let mut ed = EdgeDetector::with_filter(line.input(), 3);
+    let mut rising = |polls: usize| (0..polls).filter(|_| ed.risig_edge()).count();
+
+    // Two low samples are a glitch: the line never left high.
+    line.script(true, 3);
+    line.script(false, 10);
+    assert_eq!(rising(13), 0);
+
+    // Three are a level, so the line rises again after them.
+    line.script(true, 4);
+    line.script(false, 10);
+    assert_eq!(rising(14), 1);
+}
+
+/// Records the edges of `byte` as sent from `at`, in timer counts of ten
+/// per phase, and returns the time of the release.
+fn capture_frame(edges: &mut EdgeBuffer<64>, byte: u8, at: u32) -> u32 {
+    edges.push(Edge { rising: false, at });
+    let mut t = at + 40;
+    for i in (0..8).rev() {
+        let high = if byte >> i & 1 != 0 { 40 } else { 20 };
+        edges.push(Edge {
+            rising: true,
+            at: t,
+        });
+        edges.push(Edge {
+            rising: false,
+            at: t + high,
+        });
+        t += 80;
+    }
+    edges.push(Edge {
+        rising: true,
+        at: t,
+    });
+    t
+}
+
+#[test]
+fn read_captured_decodes_back_to_back_frames() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let mut edges = EdgeBuffer::<64>::new();
+
+    let end = capture_frame(&mut edges, 0x3c, 0);
+    capture_frame(&mut edges, 0x3c, end + 40);
+    assert_eq!(wire.read_captured(&mut edges, 10), Ok(0x3c));
+    assert_eq!(wire.read_captured(&mut edges, 10), Ok(0x3c));
+}
+
+#[test]
+fn read_captured_reports_like_read() {
+    let line = Line::new(10);
+    let config = Config {
+        bit_order: BitOrder::LsbFirst,
+        ..Config::default()
+    };
+    let mut wire = mock_wire(&line)
+        .with_config(config)
+        .with_observer(BitLog::default());
+    let mut edges = EdgeBuffer::<64>::new();
+
+    capture_frame(&mut edges, 0xc0, 0);
+    assert_eq!(wire.read_captured(&mut edges, 10), Ok(0x03));
+    assert_eq!(wire.observer().rx, bits_of(0xc0, 8));
+    assert_eq!(wire.stats().frames_received, 1);
+}
+
+#[test]
+fn rx_buffer_collects_from_every_receive_path() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_observer(RxBuffer::<4>::new());
+
+    line.script_byte(0x01);
+    wire.read(&mut line.delay()).unwrap();
+    line.script_byte(0x02);
+    let event = (0..1000).find_map(|_| {
+        line.advance(9);
+        wire.poll(line.now())
+    });
+    assert_eq!(event, Some(WireEvent::FrameReceived { len: 1 }));
+    let mut edges = EdgeBuffer::<64>::new();
+    capture_frame(&mut edges, 0x03, 0);
+    wire.read_captured(&mut edges, 10).unwrap();
+
+    let mut buf = [0u8; 4];
+    assert_eq!(wire.observer_mut().pop_into(&mut buf), 3);
+    assert_eq!(&buf[..3], &[1, 2, 3]);
+}
+
+#[test]
+fn read_captured_rejects_zero_timer_rate() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let mut edges = EdgeBuffer::<64>::new();
+
+    capture_frame(&mut edges, 0x3c, 0);
+    assert_eq!(wire.read_captured(&mut edges, 0), Err(Error::Config));
+}
+
+#[test]
+fn descriptor_covers_framing_options() {
+    let line = Line::new(10);
+    let base = mock_wire(&line).descriptor::<Crc16>();
+
+    let variants = [
+        Config {
+            parity: Parity::Odd,
+            ..Config::default()
+        },
+        Config {
+            scramble: true,
+            ..Config::default()
+        },
+        Config {
+            frame_gap: 8,
+            ..Config::default()
+        },
+    ];
+    for config in variants {
+        let other = mock_wire(&line).with_config(config).descriptor::<Crc16>();
+        assert_eq!(base.check(&other), Err(Error::IncompatiblePeer));
+    }
+    assert_eq!(base.check(&mock_wire(&line).descriptor::<Crc16>()), Ok(()));
+}
+
+#[test]
+fn receive_window_travels_both_ways() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let window = ReceiveWindow {
+        period: 1000,
+        listen: 50,
+        next: 300,
+    };
+
+    advertise_window(&mut wire, &window, &mut line.delay()).unwrap();
+    let mut bits = [false; 64];
+    let n = line.written_bits(&mut bits);
+    let mut expected = bits_of(WINDOW_ADVERT as u32, 8);
+    for byte in window.to_bytes() {
+        expected.extend(bits_of(byte as u32, 8));
+    }
+    assert_eq!(&bits[..n], &expected[..]);
+
+    line.clear_written();
+    line.script(false, 100);
+    line.script_byte(WINDOW_ADVERT);
+    for byte in window.to_bytes() {
+        line.script_byte(byte);
+    }
+    assert_eq!(fetch_window(&mut wire, &mut line.delay()), Ok(window));
+    let n = line.written_bits(&mut bits);
+    assert_eq!(&bits[..n], &bits_of(WINDOW_REQUEST as u32, 8)[..]);
+
+    let peer = window.anchor(5000);
+    assert_eq!(peer.until_open(5000), 300);
+    assert!(peer.is_open(6320));
+}
+
+/// A wire timing the line in 10 µs ticks.
+fn fine_wire(line: &Line) -> MockWire<'_> {
+    HalfDuplexWire::new(
+        line.input(),
+        LineInput::into_output as fn(_) -> _,
+        LineOutput::into_input as fn(_) -> _,
+        Micros(10u32),
+    )
+}
+
+#[test]
+fn calibrate_measures_the_master_phase() {
+    // Every tick also spends a microsecond reading the pin.
+    let line = Line::new(44);
+    let mut wire = fine_wire(&line);
+
+    line.script_byte(TRAINING);
+    let timing = wire.calibrate(&mut line.delay()).unwrap();
+    assert_eq!(timing, Timing { ticks_per_phase: 4 });
+    assert_eq!(wire.config().timing, timing);
+}
+
+#[test]
+fn calibrate_gives_up_on_a_quiet_line() {
+    let line = Line::new(10);
+    let mut wire = fine_wire(&line);
+
+    assert_eq!(wire.calibrate(&mut line.delay()), Err(Error::Calibration));
+    assert_eq!(wire.config().timing, Timing::default());
+}
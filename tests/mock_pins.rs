@@ -0,0 +1,91 @@
+//! Exact pin traffic of the blocking transfers, against embedded-hal-mock.
+use embedded_hal_mock::delay::MockNoop;
+use embedded_hal_mock::pin::{Mock, State, Transaction};
+use half_duplex_wire::units::Micros;
+use half_duplex_wire::{Error, HalfDuplexWire};
+
+/// Two idle checks, the start condition, then one pulse per bit.
+fn frame() -> Vec<Transaction> {
+    let mut expected = vec![
+        Transaction::get(State::High),
+        Transaction::get(State::High),
+        Transaction::set(State::Low),
+    ];
+    for _ in 0..8 {
+        expected.push(Transaction::set(State::High));
+        expected.push(Transaction::set(State::Low));
+    }
+    expected
+}
+
+#[test]
+fn write_drives_start_and_eight_pulses() {
+    let pin = Mock::new(&frame());
+    let mut wire = HalfDuplexWire::new(pin.clone(), |p| p, |p| p, Micros(10u32));
+
+    wire.write(0xa5, &mut MockNoop::new()).unwrap();
+
+    wire.release().unwrap().done();
+}
+
+#[test]
+fn write_backs_off_from_busy_line() {
+    let pin = Mock::new(&[Transaction::get(State::Low)]);
+    let mut wire = HalfDuplexWire::new(pin.clone(), |p| p, |p| p, Micros(10u32));
+
+    assert_eq!(wire.write(0x00, &mut MockNoop::new()), Err(Error::Busy));
+    assert_eq!(wire.stats().collisions, 1);
+
+    wire.release().unwrap().done();
+}
+
+#[test]
+fn open_drain_pin_releases_instead_of_switching() {
+    let mut expected = vec![Transaction::set(State::High)];
+    expected.extend(frame());
+    expected.push(Transaction::set(State::High));
+
+    let pin = Mock::new(&expected);
+    let mut wire = HalfDuplexWire::from_open_drain_pin(pin, Micros(10u32));
+
+    wire.write(0x42, &mut MockNoop::new()).unwrap();
+
+    wire.release().unwrap().0.done();
+}
+
+#[test]
+fn dual_pins_split_reads_and_writes() {
+    let mut driven = vec![Transaction::set(State::High)];
+    driven.extend(frame().into_iter().skip(2));
+    driven.push(Transaction::set(State::High));
+
+    let input = Mock::new(&frame()[..2]);
+    let output = Mock::new(&driven);
+    let mut wire = HalfDuplexWire::from_dual_pins(input, output, Micros(10u32));
+
+    wire.write(0x42, &mut MockNoop::new()).unwrap();
+
+    let (mut input, mut output) = wire.release().unwrap().into_parts();
+    input.done();
+    output.done();
+}
+
+thread_local! {
+    static PULL_UP: std::cell::RefCell<Vec<bool>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+fn log_pull_up(_pin: &mut Mock, on: bool) {
+    PULL_UP.with(|log| log.borrow_mut().push(on));
+}
+
+#[test]
+fn pull_up_is_off_while_driving() {
+    let pin = Mock::new(&frame());
+    let mut wire =
+        HalfDuplexWire::new(pin.clone(), |p| p, |p| p, Micros(10u32)).with_pull_up(log_pull_up);
+
+    wire.write(0x42, &mut MockNoop::new()).unwrap();
+
+    PULL_UP.with(|log| assert_eq!(*log.borrow(), [true, false, true]));
+    wire.release().unwrap().done();
+}
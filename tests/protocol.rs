@@ -0,0 +1,232 @@
+//! Scrambling, checksums, LIN headers, breaks, token passing and discovery.
+mod common;
+
+use common::{packet, script, script_then_quiet, written};
+use half_duplex_wire::config::{Config, Role};
+use half_duplex_wire::control::{TOKEN_GRANT, TOKEN_REQUEST};
+use half_duplex_wire::crc::{Crc16, CrcEngine};
+use half_duplex_wire::discovery::{DISCOVER, SLOT_PHASES};
+use half_duplex_wire::lin::{lin_checksum, protected_id, Slot};
+use half_duplex_wire::packet::{KIND_ACK, KIND_CONTROL, KIND_DATA};
+use half_duplex_wire::scrambler::Scrambler;
+use half_duplex_wire::testing::{mock_wire, Line, MockWire};
+use half_duplex_wire::{Error, BREAK_PHASES};
+
+#[test]
+fn scrambler_roundtrips() {
+    let data: Vec<u8> = (0..=255).chain([0; 16]).chain([0xff; 16]).collect();
+    let mut tx = Scrambler::new();
+    let mut rx = Scrambler::new();
+
+    let scrambled: Vec<u8> = data.iter().map(|&b| tx.scramble(b)).collect();
+    assert_ne!(&scrambled[256..272], &[0; 16]);
+    let plain: Vec<u8> = scrambled.iter().map(|&b| rx.descramble(b)).collect();
+    assert_eq!(plain, data);
+}
+
+#[test]
+fn descrambler_locks_on_after_a_lost_frame() {
+    let mut tx = Scrambler::new();
+    let mut rx = Scrambler::new();
+    tx.scramble(0x42);
+
+    let plain: Vec<u8> = [0x11, 0x22, 0x33]
+        .iter()
+        .map(|&b| rx.descramble(tx.scramble(b)))
+        .collect();
+    assert_eq!(&plain[1..], &[0x22, 0x33]);
+}
+
+#[test]
+fn crc16_check_value() {
+    let mut crc = Crc16::new();
+    crc.update(b"123456789");
+    assert_eq!(crc.finish(), 0x29b1);
+
+    crc.reset();
+    crc.update(b"1234");
+    crc.update(b"56789");
+    assert_eq!(crc.finish(), 0x29b1);
+}
+
+#[test]
+fn lin_protected_ids() {
+    assert_eq!(protected_id(0x3c), 0x3c);
+    assert_eq!(protected_id(0x3d), 0x7d);
+    assert_eq!(protected_id(0x00), 0x80);
+    assert_eq!(protected_id(0x01), 0xc1);
+    assert_eq!(lin_checksum(0x3c, &[0x01, 0x02]), !0x3fu8);
+}
+
+#[test]
+fn master_sends_only_the_header_of_a_slot() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let slot = Slot {
+        id: 0x12,
+        len: 2,
+        gap: 50,
+    };
+    let pid = protected_id(0x12);
+
+    // The slave answers once break and pid are out.
+    line.script(false, BREAK_PHASES as u32 + 100);
+    script(&line, &[0x01, 0x02, lin_checksum(pid, &[0x01, 0x02])]);
+    let mut buf = [0u8; 2];
+    wire.run_slot(slot, &mut buf, &mut line.delay()).unwrap();
+
+    assert_eq!(buf, [0x01, 0x02]);
+    assert_eq!(written(&line), [pid]);
+}
+
+#[test]
+fn slave_answers_only_its_header() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+    let pid = protected_id(0x12);
+
+    line.script(true, BREAK_PHASES as u32);
+    line.script(false, 8);
+    script(&line, &[pid]);
+    assert_eq!(wire.wait_header(&mut line.delay()), Ok(0x12));
+    assert!(written(&line).is_empty());
+
+    wire.respond(0x12, &[0x01, 0x02], &mut line.delay())
+        .unwrap();
+    assert_eq!(
+        written(&line),
+        [0x01, 0x02, lin_checksum(pid, &[0x01, 0x02])]
+    );
+}
+
+#[test]
+fn break_is_sent_and_detected() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    let start = line.now();
+    wire.send_break(BREAK_PHASES, &mut line.delay()).unwrap();
+    assert!(line.now() - start >= BREAK_PHASES as u32 * 10);
+    assert!(!line.is_low());
+    assert!(written(&line).is_empty());
+
+    line.script(true, BREAK_PHASES as u32);
+    wire.wait_for_break(BREAK_PHASES / 2, &mut line.delay())
+        .unwrap();
+    assert!(!line.is_low());
+
+    line.script(true, BREAK_PHASES as u32);
+    assert_eq!(wire.read(&mut line.delay()), Err(Error::BreakReceived));
+}
+
+#[test]
+fn lin_header_reaches_the_slave() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    line.script(true, BREAK_PHASES as u32);
+    line.script(false, 8);
+    line.script_byte(protected_id(0x3d));
+    assert_eq!(wire.wait_header(&mut line.delay()), Ok(0x3d));
+
+    line.script(true, BREAK_PHASES as u32);
+    line.script(false, 8);
+    line.script_byte(0x3d);
+    assert_eq!(wire.wait_header(&mut line.delay()), Err(Error::Framing));
+}
+
+fn token_wire(line: &Line) -> MockWire<'_> {
+    mock_wire(line).with_config(Config {
+        token_passing: true,
+        ..Config::default()
+    })
+}
+
+#[test]
+fn token_passes_between_peers() {
+    let a_line = Line::new(10);
+    let b_line = Line::new(10);
+    let mut a = token_wire(&a_line);
+    let mut b = token_wire(&b_line);
+    let mut crc = Crc16::new();
+    let mut buf = [0u8; 8];
+
+    a.claim_token();
+    assert_eq!(b.write(0x01, &mut b_line.delay()), Err(Error::Unavailable));
+
+    // B asks while A holds the token; A notices on its next receive.
+    b.request_token(&mut crc, &mut b_line.delay()).unwrap();
+    assert_eq!(written(&b_line), packet(KIND_CONTROL, 0, &[TOKEN_REQUEST]));
+    script_then_quiet(&a_line, &written(&b_line));
+    script_then_quiet(&a_line, &packet(KIND_DATA, 0, &[0x55]));
+    a.recv_packet(&mut buf, &mut crc, &mut a_line.delay())
+        .unwrap();
+    assert!(a.token_requested());
+
+    a.grant_token(&mut crc, &mut a_line.delay()).unwrap();
+    assert!(!a.has_token());
+    assert!(!a.token_requested());
+    assert_eq!(a.write(0x01, &mut a_line.delay()), Err(Error::Unavailable));
+
+    let grant = packet(KIND_CONTROL, 0, &[TOKEN_GRANT]);
+    assert!(written(&a_line).ends_with(&grant));
+    script_then_quiet(&b_line, &grant);
+    script_then_quiet(&b_line, &packet(KIND_DATA, 0, &[0x66]));
+    b.recv_packet(&mut buf, &mut crc, &mut b_line.delay())
+        .unwrap();
+    assert!(b.has_token());
+
+    b_line.clear_written();
+    b.write(0x77, &mut b_line.delay()).unwrap();
+    assert_eq!(written(&b_line), [0x77]);
+}
+
+#[test]
+fn demoted_wire_still_acks() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line).with_config(Config {
+        fallback_after: Some(2),
+        ..Config::default()
+    });
+    let mut crc = Crc16::new();
+    let mut buf = [0u8; 8];
+
+    line.script(true, 100);
+    assert_eq!(wire.write(0x01, &mut line.delay()), Err(Error::Busy));
+    assert_eq!(wire.write(0x01, &mut line.delay()), Err(Error::Demoted));
+    assert_eq!(wire.role(), Role::Listener);
+    assert_eq!(wire.write(0x01, &mut line.delay()), Err(Error::Unavailable));
+    line.advance(100 * 10);
+
+    script_then_quiet(&line, &packet(KIND_DATA, 0, &[0x42]));
+    let packet_in = wire.recv_packet(&mut buf, &mut crc, &mut line.delay());
+    assert_eq!(packet_in.unwrap().payload, [0x42]);
+    assert_eq!(written(&line), packet(KIND_ACK, 0, &[]));
+    assert_eq!(wire.role(), Role::Listener);
+}
+
+/// Phases the three bytes of the scan command take to go out, with room.
+const SCAN_COMMAND_PHASES: u32 = 230;
+
+#[test]
+fn scan_finds_the_device_in_its_slot() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    // The device at 0x11 answers in the second slot.
+    line.script(false, SCAN_COMMAND_PHASES + SLOT_PHASES as u32);
+    line.script_byte(0x11);
+    let found = wire.scan(0x10, 4, &mut line.delay()).unwrap();
+
+    assert_eq!(written(&line), [DISCOVER, 0x10, 4]);
+    assert_eq!(found.iter().collect::<Vec<_>>(), [0x11]);
+}
+
+#[test]
+fn scan_of_an_empty_bus_finds_nothing() {
+    let line = Line::new(10);
+    let mut wire = mock_wire(&line);
+
+    let found = wire.scan(0x10, 4, &mut line.delay()).unwrap();
+    assert!(found.is_empty());
+}
@@ -0,0 +1,129 @@
+//! Register reads and writes with the other side scripted on the line.
+mod common;
+
+use common::{packet, script, written};
+use half_duplex_wire::config::Config;
+use half_duplex_wire::crc::Crc16;
+use half_duplex_wire::packet::{KIND_ACK, KIND_DATA};
+use half_duplex_wire::registers::{
+    RegisterAccess, READ_REG, REQUEST_LEN, STATUS_BAD_REQUEST, STATUS_OK, STATUS_OUT_OF_RANGE,
+    WRITE_REG,
+};
+use half_duplex_wire::testing::{mock_wire, Line, MockWire};
+use half_duplex_wire::Error;
+
+/// Long enough for every packet in these tests to go out before the
+/// scripted side answers.
+const QUIET: u32 = 1200;
+
+fn wire(line: &Line) -> MockWire<'_> {
+    mock_wire(line).with_config(Config {
+        response_timeout: 2 * QUIET as u16,
+        ..Config::default()
+    })
+}
+
+/// Lets a slave over `regs` answer `request`; returns what it did and wrote.
+fn serve(regs: &mut [u8], request: &[u8]) -> (RegisterAccess, Vec<u8>) {
+    let line = Line::new(10);
+    let mut wire = wire(&line);
+    script(&line, &packet(KIND_DATA, 0, request));
+    line.script(false, QUIET);
+    script(&line, &packet(KIND_ACK, 0, &[]));
+
+    let mut buf = [0u8; REQUEST_LEN];
+    let access = wire.serve_registers(regs, &mut buf, &mut Crc16::new(), &mut line.delay());
+    (access.unwrap(), written(&line))
+}
+
+/// The ack of the request, then the reply.
+fn answer(reply: &[u8]) -> Vec<u8> {
+    let mut bytes = packet(KIND_ACK, 0, &[]);
+    bytes.extend(packet(KIND_DATA, 0, reply));
+    bytes
+}
+
+#[test]
+fn slave_serves_reads_and_writes() {
+    let mut regs = [10, 11, 12, 13];
+
+    let (access, sent) = serve(&mut regs, &[READ_REG, 1, 2]);
+    assert_eq!(access, RegisterAccess::Read { reg: 1, len: 2 });
+    assert_eq!(sent, answer(&[STATUS_OK, 11, 12]));
+
+    let (access, sent) = serve(&mut regs, &[WRITE_REG, 2, 2, 7, 8]);
+    assert_eq!(access, RegisterAccess::Write { reg: 2, len: 2 });
+    assert_eq!(sent, answer(&[STATUS_OK]));
+    assert_eq!(regs, [10, 11, 7, 8]);
+}
+
+#[test]
+fn slave_answers_out_of_range() {
+    let mut regs = [0u8; 4];
+    let (access, sent) = serve(&mut regs, &[READ_REG, 3, 2]);
+    assert_eq!(access, RegisterAccess::Rejected);
+    assert_eq!(sent, answer(&[STATUS_OUT_OF_RANGE]));
+}
+
+#[test]
+fn slave_answers_malformed_requests() {
+    let mut regs = [0u8; 4];
+    let requests: [&[u8]; 4] = [
+        &[READ_REG, 1],
+        &[0x7f, 0, 1],
+        &[WRITE_REG, 0, 3, 1],
+        &[READ_REG, 0, 1, 5],
+    ];
+    for request in requests {
+        let (access, sent) = serve(&mut regs, request);
+        assert_eq!(access, RegisterAccess::Rejected);
+        assert_eq!(sent, answer(&[STATUS_BAD_REQUEST]));
+    }
+    assert_eq!(regs, [0; 4]);
+}
+
+/// Scripts the slave's side of one request: quiet while the request goes
+/// out, the ack, then `reply`.
+fn script_slave(line: &Line, reply: &[u8]) {
+    line.script(false, QUIET);
+    script(line, &answer(reply));
+}
+
+#[test]
+fn master_reads_and_writes() {
+    let line = Line::new(10);
+    let mut wire = wire(&line);
+    let mut crc = Crc16::new();
+
+    script_slave(&line, &[STATUS_OK, 11, 12]);
+    let mut buf = [0u8; 2];
+    wire.read_registers(1, &mut buf, &mut crc, &mut line.delay())
+        .unwrap();
+    assert_eq!(buf, [11, 12]);
+    let mut expected = packet(KIND_DATA, 0, &[READ_REG, 1, 2]);
+    expected.extend(packet(KIND_ACK, 0, &[]));
+    assert_eq!(written(&line), expected);
+
+    line.clear_written();
+    line.script(false, QUIET);
+    script(&line, &packet(KIND_ACK, 1, &[]));
+    script(&line, &packet(KIND_DATA, 1, &[STATUS_OK]));
+    wire.write_registers(2, &[7, 8], &mut crc, &mut line.delay())
+        .unwrap();
+    let mut expected = packet(KIND_DATA, 1, &[WRITE_REG, 2, 2, 7, 8]);
+    expected.extend(packet(KIND_ACK, 1, &[]));
+    assert_eq!(written(&line), expected);
+}
+
+#[test]
+fn master_sees_error_status() {
+    let line = Line::new(10);
+    let mut wire = wire(&line);
+
+    script_slave(&line, &[STATUS_OUT_OF_RANGE]);
+    let mut buf = [0u8; 2];
+    assert_eq!(
+        wire.read_registers(3, &mut buf, &mut Crc16::new(), &mut line.delay()),
+        Err(Error::Framing)
+    );
+}
@@ -0,0 +1,82 @@
+//! One simulated wire shared between proxies and split halves.
+mod common;
+
+use common::written;
+use half_duplex_wire::config::Config;
+use half_duplex_wire::shared::SharedWire;
+use half_duplex_wire::testing::{mock_wire, Line, MockWire};
+use half_duplex_wire::{Error, ReadWrite};
+
+fn shared(line: &Line) -> SharedWire<MockWire<'_>> {
+    SharedWire::new(mock_wire(line).with_config(Config {
+        max_payload: 16,
+        ..Config::default()
+    }))
+}
+
+#[test]
+fn proxy_reaches_the_wire() {
+    let line = Line::new(10);
+    let bus = shared(&line);
+    let mut proxy = bus.proxy();
+
+    proxy.write(0x5a, &mut line.delay()).unwrap();
+    assert_eq!(written(&line), [0x5a]);
+
+    line.script_byte(0x3c);
+    assert_eq!(proxy.read(&mut line.delay()), Ok(0x3c));
+}
+
+#[test]
+fn proxy_is_busy_while_the_wire_is_held() {
+    let line = Line::new(10);
+    let bus = shared(&line);
+    let mut proxy = bus.proxy();
+
+    let nested = bus.lock(|_| proxy.write(0x5a, &mut line.delay()));
+    assert_eq!(nested, Ok(Err(Error::Busy)));
+    assert_eq!(written(&line), []);
+}
+
+#[test]
+fn proxy_config_comes_from_a_free_wire() {
+    let line = Line::new(10);
+    let bus = shared(&line);
+
+    assert_eq!(bus.proxy().config().max_payload, 16);
+}
+
+#[test]
+fn proxy_config_while_held_is_the_last_seen() {
+    let line = Line::new(10);
+    let bus = shared(&line);
+    let proxy = bus.proxy();
+
+    proxy.config();
+    let held = bus.lock(|_| proxy.config());
+    assert_eq!(held.map(|config| config.max_payload), Ok(16));
+}
+
+#[test]
+fn split_halves_send_and_receive() {
+    let line = Line::new(10);
+    let bus = shared(&line);
+    let (mut tx, mut rx) = bus.split();
+
+    tx.write_all(&[0x01, 0x02], &mut line.delay()).unwrap();
+    line.script_byte(0x3c);
+    assert_eq!(rx.read(&mut line.delay()), Ok(0x3c));
+    assert_eq!(written(&line), [0x01, 0x02]);
+}
+
+#[test]
+fn split_half_is_busy_while_the_wire_is_held() {
+    let line = Line::new(10);
+    let bus = shared(&line);
+    let (mut tx, mut rx) = bus.split();
+
+    let nested = bus.lock(|_| rx.read(&mut line.delay()));
+    assert_eq!(nested, Ok(Err(Error::Busy)));
+    tx.write(0x5a, &mut line.delay()).unwrap();
+    assert_eq!(written(&line), [0x5a]);
+}
@@ -0,0 +1,136 @@
+//! Echo handling of the UART backend, and the protocol layers over serial
+//! transports, against embedded-hal-mock.
+mod common;
+
+use common::packet;
+use embedded_hal_mock::serial::{Mock, Transaction};
+use half_duplex_wire::ascii::AsciiWire;
+use half_duplex_wire::config::Config;
+use half_duplex_wire::crc::{CheckedTransport, Crc16};
+use half_duplex_wire::frame::FrameTransport;
+use half_duplex_wire::packet::{PacketTransport, KIND_ACK, KIND_DATA, KIND_NAK};
+use half_duplex_wire::uart::UartBackend;
+use half_duplex_wire::units::PhaseDelay;
+use half_duplex_wire::Error;
+
+#[test]
+fn echo_is_dropped() {
+    let serial = Mock::new(&[
+        Transaction::write(0x5a),
+        Transaction::flush(),
+        Transaction::read(0x5a),
+        Transaction::read(0x17),
+    ]);
+    let mut uart = UartBackend::new(serial);
+
+    assert_eq!(uart.write(0x5a), Ok(()));
+    assert_eq!(uart.read(), Ok(0x17));
+
+    uart.release().done();
+}
+
+#[test]
+fn garbled_echo_is_collision() {
+    let serial = Mock::new(&[
+        Transaction::write(0x5a),
+        Transaction::flush(),
+        Transaction::read(0x1a),
+    ]);
+    let mut uart = UartBackend::new(serial);
+
+    assert_eq!(uart.write(0x5a), Err(Error::Busy));
+
+    uart.release().done();
+}
+
+/// The protocol layers never wait on these transports.
+struct NoDelay;
+
+impl PhaseDelay<()> for NoDelay {
+    fn delay_phase(&mut self, _phase: ()) {}
+}
+
+fn hex(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|b| format!("{b:02X}").into_bytes())
+        .collect()
+}
+
+#[test]
+fn packets_run_over_the_uart() {
+    let serial = Mock::new(&[
+        Transaction::write_many(packet(KIND_DATA, 0, &[1, 2])),
+        Transaction::read_many(packet(KIND_ACK, 0, &[])),
+        Transaction::read_many(packet(KIND_DATA, 7, &[3])),
+        Transaction::write_many(packet(KIND_ACK, 7, &[])),
+    ]);
+    let mut uart = UartBackend::without_echo(serial);
+    let mut crc = Crc16::new();
+    let mut buf = [0u8; 4];
+
+    uart.send_packet(&[1, 2], &mut crc, &mut NoDelay).unwrap();
+    let packet = uart.recv_packet(&mut buf, &mut crc, &mut NoDelay).unwrap();
+    assert_eq!((packet.seq, packet.payload), (7, &[3][..]));
+
+    uart.release().done();
+}
+
+#[test]
+fn nak_stops_the_retries() {
+    let serial = Mock::new(&[
+        Transaction::write_many(packet(KIND_DATA, 0, &[1, 2])),
+        Transaction::read_many(packet(KIND_NAK, 0, &[])),
+    ]);
+    let mut uart = UartBackend::without_echo(serial);
+
+    assert_eq!(
+        uart.send_packet(&[1, 2], &mut Crc16::new(), &mut NoDelay),
+        Err(Error::FrameTooLarge)
+    );
+
+    uart.release().done();
+}
+
+#[test]
+fn frames_and_packets_run_over_ascii() {
+    let serial = Mock::new(&[
+        Transaction::write_many(hex(&[2, 0xab, 0xcd])),
+        Transaction::read_many(hex(&packet(KIND_DATA, 1, &[0x55]))),
+        Transaction::write_many(hex(&packet(KIND_ACK, 1, &[]))),
+    ]);
+    let mut ascii = AsciiWire::new(serial);
+    let mut crc = Crc16::new();
+    let mut buf = [0u8; 4];
+
+    ascii.send_frame(&[0xab, 0xcd], &mut NoDelay).unwrap();
+    let packet = ascii.recv_packet(&mut buf, &mut crc, &mut NoDelay).unwrap();
+    assert_eq!(packet.payload, [0x55]);
+
+    ascii.release().done();
+}
+
+#[test]
+fn ascii_follows_its_config() {
+    let serial = Mock::new(&[
+        Transaction::write_many(hex(&[0x11])),
+        Transaction::read_many(hex(&[0x22])),
+    ]);
+    let config = Config {
+        crc: false,
+        ..Config::default()
+    };
+    let mut ascii = AsciiWire::new(serial).with_config(config);
+    let mut crc = Crc16::new();
+    let mut buf = [0u8; 1];
+
+    ascii
+        .write_checked(&[0x11], &mut crc, &mut NoDelay)
+        .unwrap();
+    ascii
+        .read_checked(&mut buf, &mut crc, &mut NoDelay)
+        .unwrap();
+    assert_eq!(buf, [0x22]);
+
+    ascii.release().done();
+}